@@ -0,0 +1,45 @@
+use redb_bincode::*;
+
+fn main() -> anyhow::Result<()> {
+    let db = Database::from(redb::Database::create("concurrent_read_db")?);
+
+    let write_txn = db.begin_write()?;
+    {
+        let mut table = write_txn.open_table::<u64, u64>("events")?;
+        for i in 0..100u64 {
+            table.insert(&i, &i)?;
+        }
+    }
+    write_txn.commit()?;
+
+    let read_txn = db.begin_read()?;
+    let table = read_txn.open_table::<u64, u64>("events")?;
+    let mut iter = table.key_iter()?;
+    assert_eq!(iter.next().transpose()?, Some(0));
+
+    // Write while the iterator is live: the reader keeps its snapshot.
+    std::thread::scope(|s| {
+        s.spawn(|| -> anyhow::Result<()> {
+            let write_txn = db.begin_write()?;
+            {
+                let mut table = write_txn.open_table::<u64, u64>("events")?;
+                table.insert(&0u64, &1000u64)?;
+                table.insert(&1000u64, &1000u64)?;
+            }
+            write_txn.commit()?;
+            Ok(())
+        })
+        .join()
+        .expect("writer panicked")
+    })?;
+
+    let rest = iter.collect::<Result<Vec<u64>, _>>()?;
+    println!("read {} more keys from the snapshot", rest.len());
+    assert_eq!(rest, (1..100).collect::<Vec<_>>());
+    assert_eq!(table.get(&0u64)?.expect("some").value()?, 0);
+
+    let read_txn = db.begin_read()?;
+    let table = read_txn.open_table::<u64, u64>("events")?;
+    assert_eq!(table.get(&0u64)?.expect("some").value()?, 1000);
+    Ok(())
+}
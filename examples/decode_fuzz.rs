@@ -0,0 +1,66 @@
+use redb_bincode::*;
+
+/// A xorshift generator, enough to produce arbitrary value bytes.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let db = Database::from(redb::Database::create("decode_fuzz_db")?);
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+    let ops = (0..10_000u64)
+        .map(|i| {
+            let len = (rng.next() % 64) as usize;
+            Op::Insert {
+                table: "fuzz".to_owned(),
+                key: bincode::encode_to_vec(i, KEY_CONFIG).expect("encoding can't fail"),
+                value: (0..len).map(|_| rng.next() as u8).collect(),
+            }
+        })
+        .collect();
+    db.apply(ops)?;
+
+    // Random bytes often claim huge lengths: every decode must fail cleanly
+    // rather than try to allocate them.
+    let read_txn = db.begin_read()?;
+    let table = read_txn.open_table::<u64, Vec<String>>("fuzz")?;
+    let (mut ok, mut limited, mut malformed) = (0, 0, 0);
+    for i in 0..10_000u64 {
+        match table.try_get::<4096, _>(&i) {
+            Ok(value) => {
+                assert!(value.is_some());
+                ok += 1;
+            }
+            Err(TryGetError::DecodeLimitExceeded) => limited += 1,
+            Err(TryGetError::Decode(_)) => malformed += 1,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    println!("{ok} decoded, {limited} over the limit, {malformed} malformed");
+    assert!(limited > 0);
+    drop(read_txn);
+
+    // A short value can claim a huge length too: a table opened with a limit
+    // must not allocate past it either.
+    db.apply(vec![Op::Insert {
+        table: "huge".to_owned(),
+        key: bincode::encode_to_vec(0u64, KEY_CONFIG).expect("encoding can't fail"),
+        value: bincode::encode_to_vec(1u64 << 40, VALUE_CONFIG).expect("encoding can't fail"),
+    }])?;
+    let read_txn = db.begin_read()?;
+    let table = read_txn.open_table_limited::<u64, Vec<u64>>("huge", 64)?;
+    let value = table.get(&0)?.expect("inserted above");
+    assert!(matches!(
+        value.value(),
+        Err(bincode::error::DecodeError::LimitExceeded)
+    ));
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::time::Instant;
+
+use redb::ReadableTable;
+use redb_bincode::*;
+
+const ROWS: u64 = 200_000;
+const START: usize = 50_000;
+const END: usize = 150_000;
+const RUNS: u32 = 10;
+
+fn main() -> anyhow::Result<()> {
+    let db = Database::from(redb::Database::create("get_many_window_db")?);
+
+    let write_txn = db.begin_write()?;
+    {
+        let mut table = write_txn.open_table::<u64, u64>("rows")?;
+        for i in 0..ROWS {
+            table.insert(&i, &(i * 2))?;
+        }
+    }
+    write_txn.commit()?;
+
+    let read_txn = db.begin_read()?;
+    let table = read_txn.open_table::<u64, u64>("rows")?;
+
+    // What get_many did before it reserved the window: push into an empty
+    // vector, growing it as it fills.
+    let unreserved = || -> anyhow::Result<Vec<(u64, u64)>> {
+        let mut res = vec![];
+        for entry in table.as_raw().iter()?.skip(START).take(END - START) {
+            let (key, value) = entry?;
+            let (key, _) = bincode::decode_from_slice(key.value(), KEY_CONFIG)?;
+            let (value, _) = bincode::decode_from_slice(value.value(), VALUE_CONFIG)?;
+            res.push((key, value));
+        }
+        Ok(res)
+    };
+    let reserved = || table.get_many(Some(START), Some(END));
+
+    assert_eq!(unreserved()?, reserved()?);
+    assert_eq!(reserved()?.capacity(), END - START);
+
+    let started = Instant::now();
+    for _ in 0..RUNS {
+        unreserved()?;
+    }
+    let before = started.elapsed() / RUNS;
+
+    let started = Instant::now();
+    for _ in 0..RUNS {
+        reserved()?;
+    }
+    let after = started.elapsed() / RUNS;
+
+    println!(
+        "{} row window: {before:?} growing the result, {after:?} reserving it",
+        END - START
+    );
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use redb_bincode::*;
+
+fn main() -> anyhow::Result<()> {
+    let db = Database::from(redb::Database::create("range_sampled_db")?);
+
+    let write_txn = db.begin_write()?;
+    {
+        let mut table = write_txn.open_table::<u64, u64>("series")?;
+        for i in 0..1000u64 {
+            table.insert(&i, &(i * i))?;
+        }
+    }
+    write_txn.commit()?;
+
+    let read_txn = db.begin_read()?;
+    let table = read_txn.open_table::<u64, u64>("series")?;
+
+    // Every 10th entry, starting with the first.
+    let sampled = table.get_range_sampled(.., 10)?;
+    println!("sampled {} of 1000 rows", sampled.len());
+    assert_eq!(sampled.len(), 100);
+    assert_eq!(
+        sampled,
+        (0..1000u64)
+            .step_by(10)
+            .map(|i| (i, i * i))
+            .collect::<Vec<_>>()
+    );
+
+    // The step applies within the range too.
+    let sampled = table.get_range_sampled(5..25, 10)?;
+    assert_eq!(sampled, vec![(5, 25), (15, 225)]);
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use bincode::config::{LittleEndian, Varint};
+use redb_bincode::*;
+
+/// Same keys as the default encoding, little-endian values.
+type LittleEndianValues = Bincode<LittleEndian, Varint>;
+
+fn main() -> anyhow::Result<()> {
+    let db = Database::from(redb::Database::create("reencode_db")?);
+
+    // Large values, so that their varint encodings depend on the endianness.
+    let rows: Vec<(u64, u64)> = (0..100u64).map(|i| (i, i << 40)).collect();
+    let write_txn = db.begin_write()?;
+    {
+        let mut table = write_txn.open_table::<u64, u64>("values")?;
+        for (key, value) in &rows {
+            table.insert(key, value)?;
+        }
+    }
+    write_txn.commit()?;
+
+    let count = db.reencode_table::<u64, u64>(
+        "values",
+        VALUE_CONFIG,
+        <LittleEndianValues as Encoding>::VALUE,
+    )?;
+    assert_eq!(count, rows.len() as u64);
+
+    let read_txn = db.begin_read()?;
+    let table = read_txn.open_table_with_encoding::<u64, u64, LittleEndianValues>("values")?;
+    assert_eq!(table.get_range::<u64, _>(..)?, rows);
+    // The default encoding reads the swapped bytes as other values.
+    let table = read_txn.open_table::<u64, u64>("values")?;
+    assert_ne!(table.get(&1)?.expect("some").value()?, 1 << 40);
+    drop(read_txn);
+
+    // And back, to the same rows.
+    db.reencode_table::<u64, u64>(
+        "values",
+        <LittleEndianValues as Encoding>::VALUE,
+        VALUE_CONFIG,
+    )?;
+    let read_txn = db.begin_read()?;
+    let table = read_txn.open_table::<u64, u64>("values")?;
+    assert_eq!(table.get_range::<u64, _>(..)?, rows);
+    println!("re-encoded {count} rows and back");
+    Ok(())
+}
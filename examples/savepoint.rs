@@ -0,0 +1,41 @@
+use redb_bincode::*;
+
+fn main() -> anyhow::Result<()> {
+    let path = "savepoint_db";
+
+    let id = {
+        let db = Database::from(redb::Database::create(path)?);
+
+        let write_txn = db.begin_write()?;
+        write_txn
+            .open_table::<String, u64>("counts")?
+            .insert("foo", &1u64)?;
+        write_txn.commit()?;
+
+        let write_txn = db.begin_write()?;
+        let id = write_txn.persistent_savepoint()?;
+        write_txn.commit()?;
+
+        let write_txn = db.begin_write()?;
+        write_txn
+            .open_table::<String, u64>("counts")?
+            .insert("foo", &2u64)?;
+        write_txn.commit()?;
+        id
+    };
+
+    // Reopen the database, as after a restart.
+    let db = Database::from(redb::Database::create(path)?);
+    db.restore_from_savepoint(id)?;
+
+    let read_txn = db.begin_read()?;
+    let table = read_txn.open_table::<String, u64>("counts")?;
+    let v = table.get("foo")?.expect("some").value()?;
+    println!("restored: {:?}", v);
+    assert_eq!(v, 1);
+
+    let write_txn = db.begin_write()?;
+    write_txn.delete_persistent_savepoint(id)?;
+    write_txn.commit()?;
+    Ok(())
+}
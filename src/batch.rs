@@ -0,0 +1,92 @@
+use std::marker::PhantomData;
+
+use crate::{Database, KEY_CONFIG, VALUE_CONFIG};
+
+/// Buffers inserts into a table and commits them in write transactions of
+/// `chunk` entries each, see [`Database::batch_writer`]. Entries are encoded
+/// with the default [`Bincode`](crate::Bincode) encoding.
+///
+/// Call [`BatchWriter::finish`] to commit the last entries and see whether
+/// that succeeded. A writer dropped with uncommitted entries commits them
+/// too, ignoring errors, unless it is dropped by an unwinding panic, so that
+/// a panic doesn't commit a partial batch. The entries of a chunk that failed
+/// to commit are discarded.
+pub struct BatchWriter<'db, K, V>
+where
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+{
+    db: &'db Database,
+    table: String,
+    chunk: usize,
+    pending: Vec<(Vec<u8>, Vec<u8>)>,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+}
+
+impl<'db, K, V> BatchWriter<'db, K, V>
+where
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+{
+    pub(crate) fn new(db: &'db Database, table: &str, chunk: usize) -> Self {
+        Self {
+            db,
+            table: table.to_owned(),
+            chunk: chunk.max(1),
+            pending: Vec::with_capacity(chunk.max(1)),
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+
+    /// Add an entry, committing the buffered entries once there are `chunk`
+    /// of them.
+    pub fn push(&mut self, key: &K, value: &V) -> Result<(), redb::Error> {
+        self.pending.push((
+            bincode::encode_to_vec(key, KEY_CONFIG).expect("encoding can't fail"),
+            bincode::encode_to_vec(value, VALUE_CONFIG).expect("encoding can't fail"),
+        ));
+        if self.pending.len() >= self.chunk {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Commit the remaining entries.
+    pub fn finish(mut self) -> Result<(), redb::Error> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), redb::Error> {
+        // Taken first, so that a failed chunk isn't committed again on drop.
+        let pending = std::mem::take(&mut self.pending);
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let txn = self.db.begin_write()?;
+        {
+            let mut table = txn.open_table::<K, V>(&self.table)?;
+            for (key, value) in &pending {
+                table.insert_bytes(key, value)?;
+            }
+        }
+        txn.commit()?;
+        // Reuse the buffer for the next chunk.
+        self.pending = pending;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl<K, V> Drop for BatchWriter<'_, K, V>
+where
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+{
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            let _ = self.flush();
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use redb::{ReadableTable, ReadableTableMetadata, StorageError};
+
+use super::{
+    fnv1a, with_encode_key_buf, AccessGuard, Lexicographical, SortOrder, Table, FNV_OFFSET,
+    KEY_CONFIG,
+};
+
+/// Bits per expected key, for a false positive rate of about 1% with
+/// [`HASHES`] hash functions.
+const BITS_PER_KEY: usize = 10;
+const HASHES: u64 = 7;
+
+/// A bloom filter over encoded keys, along with the generation of the table
+/// it was persisted at.
+#[derive(bincode::Encode, bincode::Decode)]
+pub(crate) struct Bloom {
+    generation: u64,
+    bits: Vec<u64>,
+}
+
+impl Bloom {
+    fn with_capacity(keys: usize) -> Self {
+        Self {
+            generation: 0,
+            bits: vec![0; (keys.max(1) * BITS_PER_KEY).div_ceil(64)],
+        }
+    }
+
+    fn positions(&self, key: &[u8]) -> impl Iterator<Item = usize> {
+        let len = self.bits.len() as u64 * 64;
+        let h1 = fnv1a(FNV_OFFSET, key);
+        let h2 = fnv1a(h1, key) | 1;
+        (0..HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    fn insert(&mut self, key: &[u8]) {
+        for pos in self.positions(key) {
+            self.bits[pos / 64] |= 1 << (pos % 64);
+        }
+    }
+
+    fn may_contain(&self, key: &[u8]) -> bool {
+        self.positions(key)
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
+}
+
+/// A mutable table with an in-memory bloom filter of its keys, so that
+/// lookups of absent keys mostly don't touch the table.
+///
+/// The filter is persisted in a `<name>.bloom` sidecar table by
+/// [`flush`](Self::flush), along with the table's generation, which counts
+/// every change made through a [`Table`] handle. The next open rebuilds the
+/// filter from the keys if the table changed since, so writes through other
+/// handles only cost a rebuild, and dropping the handle without flushing
+/// loses the filter's updates. Writes that bypass [`Table`], such as through
+/// [`Table::as_raw_mut`], aren't counted and may make keys be reported as
+/// absent.
+///
+/// The filter hashes keys as encoded with [`KEY_CONFIG`], so only tables of
+/// the default encoding are supported.
+pub struct BloomTable<'txn, K, V, S>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+{
+    inner: Table<'txn, K, V, S>,
+    sidecar: Table<'txn, (), Bloom, Lexicographical>,
+    /// The generation of the table as of the last commit.
+    committed_generation: u64,
+    bloom: Bloom,
+}
+
+impl<'txn, K, V, S> BloomTable<'txn, K, V, S>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+{
+    pub(crate) fn new(
+        inner: Table<'txn, K, V, S>,
+        sidecar: Table<'txn, (), Bloom, Lexicographical>,
+        committed_generation: u64,
+        expected_keys: usize,
+    ) -> Result<Self, redb::Error> {
+        let generation = committed_generation + inner.changes.writes();
+        let len = inner.inner.len()?;
+        let persisted = sidecar
+            .get(&())?
+            .map(|v| v.value())
+            .transpose()
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+
+        let bloom = match persisted {
+            Some(bloom) if bloom.generation == generation && !bloom.bits.is_empty() => bloom,
+            _ => {
+                let mut bloom = Bloom::with_capacity(expected_keys.max(len as usize));
+                bloom.generation = generation;
+                for entry in inner.inner.iter()? {
+                    let (key, _) = entry?;
+                    bloom.insert(key.value());
+                }
+                bloom
+            }
+        };
+
+        Ok(Self {
+            inner,
+            sidecar,
+            committed_generation,
+            bloom,
+        })
+    }
+
+    /// Returns the underlying table.
+    pub fn as_table(&self) -> &Table<'txn, K, V, S> {
+        &self.inner
+    }
+
+    /// Returns whether the table contains the key.
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool, StorageError>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Get a value from the table by key.
+    /// Keys that are definitely absent are answered from the filter alone.
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        unsafe {
+            Ok(with_encode_key_buf(|buf| {
+                let size = bincode::encode_into_std_write(key, buf, KEY_CONFIG)
+                    .expect("encoding can't fail");
+                if !self.bloom.may_contain(&buf[..size]) {
+                    return Ok(None);
+                }
+                self.inner.inner.get(&buf[..size])
+            })?
+            .map(AccessGuard::from))
+        }
+    }
+
+    /// Inserts a key and value into the table.
+    /// Returns the previous value, if any.
+    pub fn insert<KQ, VQ>(
+        &mut self,
+        key: &KQ,
+        value: &VQ,
+    ) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KQ: bincode::Encode + ?Sized,
+        VQ: bincode::Encode + ?Sized,
+    {
+        let key = self.inner.encode_key(key);
+        self.bloom.insert(key.as_bytes());
+        self.inner.insert_raw(&key, value)
+    }
+
+    /// Remove a value from the table by key.
+    /// Returns the value that was removed, if any.
+    pub fn remove<KQ>(&mut self, key: &KQ) -> Result<Option<AccessGuard<'_, V>>, redb::Error>
+    where
+        K: Borrow<KQ>,
+        KQ: bincode::Encode + ?Sized,
+    {
+        self.inner.remove(key)
+    }
+
+    /// Persist the filter to the sidecar table.
+    /// Call this before dropping the handle, otherwise the next open rebuilds
+    /// the filter from the table's keys.
+    pub fn flush(&mut self) -> Result<(), StorageError> {
+        // The generation the table will have once this transaction commits,
+        // unless it is changed again before.
+        self.bloom.generation = self.committed_generation + self.inner.changes.writes();
+        self.sidecar.insert(&(), &self.bloom)?;
+        Ok(())
+    }
+}
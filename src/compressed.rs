@@ -0,0 +1,137 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use super::{decode_value, with_encode_key_buf, with_encode_value_buf, SortOrder, Table};
+use super::{KEY_CONFIG, VALUE_CONFIG};
+
+/// Prefix of a value stored as zstd-compressed encoded bytes. Values stored
+/// without it are plain encoded values.
+///
+/// For any value whose encoding starts with an integer, a length, a bool or
+/// an enum variant, a leading `0xff` isn't valid bincode, so plain values
+/// can't start with this prefix. The few that can, such as values starting
+/// with a `u8`, are always stored compressed when they do.
+const ZSTD_MAGIC: &[u8; 8] = b"\xffzstd\x00rb";
+
+/// Largest value that can be decompressed, so that a corrupt or malicious
+/// value can't claim a huge decompressed size. Inserting larger values fails.
+const MAX_DECOMPRESSED_BYTES: usize = 256 << 20;
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> redb::Error {
+    redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// A mutable table whose values are compressed with zstd.
+///
+/// Each value is compressed unless that doesn't make it smaller, in which
+/// case it is stored as plain encoded bytes. Compressed values start with a
+/// magic prefix, so values written without this wrapper can still be read
+/// through it. Values can't decompress to more than 256 MiB.
+///
+/// Values are encoded with [`VALUE_CONFIG`] before compression, so only tables
+/// of the default encoding are supported.
+pub struct CompressedTable<'txn, K, V, S>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    inner: Table<'txn, K, V, S>,
+    level: i32,
+}
+
+impl<'txn, K, V, S> CompressedTable<'txn, K, V, S>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+{
+    pub(crate) fn new(inner: Table<'txn, K, V, S>, level: i32) -> Self {
+        Self { inner, level }
+    }
+
+    /// Returns the underlying table. Its values can't be decoded directly.
+    pub fn as_table(&self) -> &Table<'txn, K, V, S> {
+        &self.inner
+    }
+
+    /// Get a value from the table by key.
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        self.inner
+            .get(key)?
+            .map(|v| Self::decode(v.inner.value()))
+            .transpose()
+    }
+
+    /// Inserts a key and value into the table.
+    pub fn insert<KQ, VQ>(&mut self, key: &KQ, value: &VQ) -> Result<(), redb::Error>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KQ: bincode::Encode + ?Sized,
+        VQ: bincode::Encode + ?Sized,
+    {
+        unsafe {
+            with_encode_key_buf(|key_buf| {
+                let key_size = bincode::encode_into_std_write(key, key_buf, KEY_CONFIG)
+                    .expect("encoding can't fail");
+
+                with_encode_value_buf(|value_buf| {
+                    let value_size = bincode::encode_into_std_write(value, value_buf, VALUE_CONFIG)
+                        .expect("encoding can't fail");
+                    if value_size > MAX_DECOMPRESSED_BYTES {
+                        return Err(redb::StorageError::ValueTooLarge(value_size).into());
+                    }
+
+                    let compressed = zstd::bulk::compress(&value_buf[..value_size], self.level)
+                        .map_err(redb::Error::Io)?;
+                    // A plain value that happens to start with the prefix
+                    // must be compressed to read back.
+                    if ZSTD_MAGIC.len() + compressed.len() < value_size
+                        || value_buf.starts_with(ZSTD_MAGIC)
+                    {
+                        value_buf.clear();
+                        value_buf.extend_from_slice(ZSTD_MAGIC);
+                        value_buf.extend_from_slice(&compressed);
+                    }
+
+                    self.inner.insert_bytes(&key_buf[..key_size], value_buf)?;
+                    Ok(())
+                })
+            })
+        }
+    }
+
+    /// Remove a value from the table by key.
+    /// Returns the value that was removed, if any.
+    pub fn remove<KQ>(&mut self, key: &KQ) -> Result<Option<V>, redb::Error>
+    where
+        K: Borrow<KQ>,
+        KQ: bincode::Encode + ?Sized,
+    {
+        self.inner
+            .remove(key)?
+            .map(|v| Self::decode(v.inner.value()))
+            .transpose()
+    }
+
+    fn decode(data: &[u8]) -> Result<V, redb::Error> {
+        use std::io::Read;
+
+        let Some(compressed) = data.strip_prefix(ZSTD_MAGIC) else {
+            return decode_value(data);
+        };
+        let mut decompressed = vec![];
+        zstd::stream::read::Decoder::new(compressed)
+            .map_err(redb::Error::Io)?
+            .take(MAX_DECOMPRESSED_BYTES as u64 + 1)
+            .read_to_end(&mut decompressed)
+            .map_err(redb::Error::Io)?;
+        if decompressed.len() > MAX_DECOMPRESSED_BYTES {
+            return Err(invalid_data("compressed value is too large"));
+        }
+        decode_value(&decompressed)
+    }
+}
@@ -0,0 +1,39 @@
+use super::{Lexicographical, Table};
+
+/// Named monotonic counters, such as the next id of a table.
+///
+/// Counters live in a `Table<String, u64>` and are updated within the write
+/// transaction they were opened in.
+pub struct Counters<'txn> {
+    inner: Table<'txn, String, u64, Lexicographical>,
+}
+
+impl<'txn> From<Table<'txn, String, u64, Lexicographical>> for Counters<'txn> {
+    fn from(inner: Table<'txn, String, u64, Lexicographical>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Counters<'_> {
+    /// Increment a counter and return its new value.
+    /// Counters start at 0, so the first call returns 1. Fails, leaving the
+    /// counter unchanged, once it would overflow `u64::MAX`.
+    pub fn next(&mut self, name: &str) -> Result<u64, redb::Error> {
+        let next = self.get(name)?.checked_add(1).ok_or_else(|| {
+            redb::Error::Io(std::io::Error::other(format!("counter {name} overflowed")))
+        })?;
+        self.inner.insert(name, &next)?;
+        Ok(next)
+    }
+
+    /// Get the current value of a counter, 0 if it was never incremented.
+    pub fn get(&self, name: &str) -> Result<u64, redb::Error> {
+        Ok(self
+            .inner
+            .get(name)?
+            .map(|v| v.value())
+            .transpose()
+            .map_err(|e| redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?
+            .unwrap_or(0))
+    }
+}
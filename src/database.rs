@@ -1,12 +1,118 @@
-use std::path::Path;
+use std::cmp::Ordering;
+use std::ops::RangeBounds;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU64;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
 
 use bincode::{Decode, Encode};
-use redb::{ReadableTableMetadata, TableHandle, TableStats, TransactionError, UntypedTableHandle};
+use redb::{
+    ReadableTable, ReadableTableMetadata, TableDefinition, TableHandle, TableStats,
+    TransactionError, UntypedTableHandle,
+};
 
-use super::tx::{ReadTransaction, WriteTransaction};
-use crate::tx;
+use super::schema::{COMMITS, GENERATIONS, TYPE_TAGS};
+use super::tx::WriteTransaction;
+use super::watch::{ChangeEvent, Subscribers};
+use crate::{
+    decode_key, decode_value, tx, BatchWriter, ChangeKind, EncodedRange, Lexicographical, Retrying,
+    Schema, SortKey, SortOrder, TableDescription, TableSnapshot, KEY_CONFIG,
+};
 
-pub struct Database(redb::Database);
+/// The difference between two tables, see [`Database::diff_tables`].
+#[derive(Debug)]
+pub struct TableDiff<K, V> {
+    /// Entries only present in the first table.
+    pub only_in_a: Vec<(K, V)>,
+    /// Entries only present in the second table.
+    pub only_in_b: Vec<(K, V)>,
+    /// Keys present in both tables, with the differing values from each.
+    pub changed: Vec<(K, V, V)>,
+}
+
+/// A mutation of a table in raw encoded bytes, see [`Database::apply`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Insert {
+        table: String,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Remove {
+        table: String,
+        key: Vec<u8>,
+    },
+}
+
+/// What [`Database::merge_from`] does with a row whose key already has
+/// another value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the existing value.
+    KeepExisting,
+    /// Replace the existing value.
+    Overwrite,
+    /// Fail, and merge nothing.
+    Error,
+}
+
+/// The fields of [`TableStats`], with serde support under the `serde`
+/// feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableStatsDto {
+    pub tree_height: u32,
+    pub leaf_pages: u64,
+    pub branch_pages: u64,
+    pub stored_bytes: u64,
+    pub metadata_bytes: u64,
+    pub fragmented_bytes: u64,
+}
+
+impl From<TableStats> for TableStatsDto {
+    fn from(stats: TableStats) -> Self {
+        Self {
+            tree_height: stats.tree_height(),
+            leaf_pages: stats.leaf_pages(),
+            branch_pages: stats.branch_pages(),
+            stored_bytes: stats.stored_bytes(),
+            metadata_bytes: stats.metadata_bytes(),
+            fragmented_bytes: stats.fragmented_bytes(),
+        }
+    }
+}
+
+fn read_only_error() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        "database was opened read-only",
+    )
+}
+
+/// The outcome of [`Database::vacuum`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VacuumReport {
+    /// Whether the integrity check found corruption and repaired it.
+    pub repaired: bool,
+    /// Whether compaction freed any space.
+    pub compacted: bool,
+    /// How much the file shrank, unknown if the database was converted from
+    /// redb and so its path is unknown.
+    pub reclaimed_bytes: Option<u64>,
+}
+
+pub struct Database {
+    inner: redb::Database,
+    subscribers: Subscribers,
+    read_only: bool,
+    /// Write guards dropped without being ended, see
+    /// [`Database::abandoned_write_guards`].
+    abandoned_write_guards: Arc<AtomicU64>,
+    /// The file backing the database, unknown if it was converted from redb.
+    path: Option<PathBuf>,
+}
 
 impl Database {
     /// Creates a new database with the given name and cache size.
@@ -14,9 +120,56 @@ impl Database {
     pub fn new(name: impl AsRef<Path>, cache_size: Option<usize>) -> Self {
         let db = redb::Database::builder()
             .set_cache_size(cache_size.unwrap_or(4 * 1024 * 1024 * 1024))
-            .create(name)
+            .create(name.as_ref())
             .unwrap();
-        Database(db)
+        Self {
+            path: Some(name.as_ref().to_owned()),
+            ..Database::from(db)
+        }
+    }
+
+    /// Opens an existing database that refuses writes: [`Database::begin_write`]
+    /// fails with a [`PermissionDenied`](std::io::ErrorKind::PermissionDenied)
+    /// I/O error, and so do all methods that write.
+    ///
+    /// This is only enforced by this crate. redb still opens the file for
+    /// writing and locks it exclusively, so it can't be shared with another
+    /// process that has it open, which fails with
+    /// [`DatabaseError::DatabaseAlreadyOpen`](redb::DatabaseError::DatabaseAlreadyOpen).
+    /// Opening a file that wasn't closed cleanly also repairs it, which
+    /// writes. Writes through [`Database::as_raw`] aren't prevented either.
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self, redb::DatabaseError> {
+        Ok(Self {
+            read_only: true,
+            path: Some(path.as_ref().to_owned()),
+            ..Database::from(redb::Database::open(path.as_ref())?)
+        })
+    }
+
+    /// Returns the size of the database file on disk, which includes the
+    /// space redb hasn't reclaimed yet, see [`Database::vacuum`].
+    ///
+    /// Fails with [`Unsupported`](std::io::ErrorKind::Unsupported) if the
+    /// database was converted from redb, whose file is unknown.
+    pub fn file_size(&self) -> Result<u64, std::io::Error> {
+        let path = self.path.as_deref().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "database was converted from redb, its file is unknown",
+            )
+        })?;
+        Ok(std::fs::metadata(path)?.len())
+    }
+
+    /// Returns the underlying redb database.
+    pub fn as_raw(&self) -> &redb::Database {
+        &self.inner
+    }
+
+    /// Returns the underlying redb database. Subscribers registered with
+    /// [`Database::subscribe`] stop receiving changes.
+    pub fn into_raw(self) -> redb::Database {
+        self.inner
     }
 
     fn table_iterator(&self) -> Result<impl Iterator<Item = UntypedTableHandle>, redb::Error> {
@@ -34,6 +187,40 @@ impl Database {
         Ok(res)
     }
 
+    /// Describe every table, from a single read transaction.
+    pub fn describe(&self) -> Result<Vec<TableDescription>, redb::Error> {
+        let txn = self.begin_read()?;
+        let tags = match txn.open_table::<String, u64>(TYPE_TAGS) {
+            Ok(tags) => Some(tags),
+            Err(redb::TableError::TableDoesNotExist(_)) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut res = Vec::new();
+        for table in txn.list_tables()? {
+            let name = table.name().to_string();
+            if [TYPE_TAGS, GENERATIONS, COMMITS].contains(&name.as_str()) {
+                continue;
+            }
+            let type_fingerprint = match &tags {
+                Some(tags) => tags
+                    .get(&name)?
+                    .map(|v| v.value())
+                    .transpose()
+                    .map_err(|e| {
+                        redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+                    })?,
+                None => None,
+            };
+            res.push(TableDescription {
+                len: txn.as_raw().open_untyped_table(table)?.len()?,
+                name,
+                type_fingerprint,
+            });
+        }
+        Ok(res)
+    }
+
     pub fn delete_table(&self, name: &str) -> Result<bool, redb::Error> {
         for table in self.table_iterator()? {
             if table.name() == name {
@@ -43,19 +230,558 @@ impl Database {
         Ok(false)
     }
 
+    /// Start buffering inserts into `table`, committed every `chunk`
+    /// entries, see [`BatchWriter`].
+    pub fn batch_writer<K, V>(&self, table: &str, chunk: usize) -> BatchWriter<'_, K, V>
+    where
+        K: Encode + Decode,
+        V: Encode + Decode,
+    {
+        BatchWriter::new(self, table, chunk)
+    }
+
+    /// Create a table if it doesn't exist yet.
+    pub fn create_table<K, V>(&self, name: &str) -> Result<(), redb::Error>
+    where
+        K: Encode + Decode,
+        V: Encode + Decode,
+    {
+        let txn = self.begin_write()?;
+        txn.create_table::<K, V>(name)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Create the named tables that don't exist yet, in a single write
+    /// transaction. Their types are unknown, so unlike
+    /// [`Database::create_table`], this doesn't record them for
+    /// [`Database::describe`]: use [`Database::ensure_schema`] for that.
+    pub fn create_tables(&self, names: &[&str]) -> Result<(), redb::Error> {
+        let txn = self.begin_write()?;
+        for name in names {
+            txn.open_raw_table(name)?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Create the tables of a schema that don't exist yet, in a single write
+    /// transaction.
+    pub fn ensure_schema<T: Schema>(&self) -> Result<(), redb::Error> {
+        let txn = self.begin_write()?;
+        T::create_tables(&txn)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Rename a table. Fails if `new_name` already exists.
+    pub fn rename_table(&self, name: &str, new_name: &str) -> Result<(), redb::Error> {
+        let txn = self.begin_write()?;
+        txn.rename_table(name, new_name)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Swap the contents of two tables.
+    /// The swap happens in a single write transaction, so readers see either
+    /// both tables before or both tables after it.
+    pub fn swap_tables(&self, a: &str, b: &str) -> Result<(), redb::Error> {
+        let tmp = format!("{a}.{b}.swap");
+        let txn = self.begin_write()?;
+        txn.rename_table(a, &tmp)?;
+        txn.rename_table(b, a)?;
+        txn.rename_table(&tmp, b)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Rewrite a table in sort order into a fresh table that replaces it, in
+    /// a single write transaction. After many random inserts this packs the
+    /// table's pages densely, without compacting the whole database.
+    /// Returns the number of entries.
+    ///
+    /// The rewrite goes through a `<name>.defrag` table, so this fails if a
+    /// table of that name exists.
+    pub fn defragment_table(&self, name: &str) -> Result<u64, redb::Error> {
+        let tmp = format!("{name}.defrag");
+        let txn = self.begin_write()?;
+        if txn.table_exists(&tmp)? {
+            return Err(redb::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("table {tmp} already exists"),
+            )));
+        }
+        let count = {
+            let src = txn.open_raw_table(name)?;
+            let mut dst = txn.open_raw_table(&tmp)?;
+            let mut count = 0;
+            for entry in src.iter()? {
+                let (key, value) = entry?;
+                dst.insert(key.value(), value.value())?;
+                count += 1;
+            }
+            count
+        };
+        txn.delete_table(TableDefinition::<SortKey<Lexicographical>, &[u8]>::new(
+            name,
+        ))?;
+        txn.rename_table(&tmp, name)?;
+        txn.commit()?;
+        Ok(count)
+    }
+
+    /// Apply `ops` in order in a single write transaction, so either all of
+    /// them are committed or none are. Keys and values are stored as given,
+    /// encode them with the [`Encoding`](crate::Encoding) of their table,
+    /// [`KEY_CONFIG`](crate::KEY_CONFIG) and
+    /// [`VALUE_CONFIG`](crate::VALUE_CONFIG) by default, to read them back as
+    /// types.
+    pub fn apply(&self, ops: Vec<Op>) -> Result<(), redb::Error> {
+        let txn = self.begin_write()?;
+        for op in ops {
+            // Only raw bytes go through the table, its types are unused.
+            match op {
+                Op::Insert { table, key, value } => {
+                    txn.open_table::<(), ()>(&table)?
+                        .insert_bytes(&key, &value)?;
+                }
+                Op::Remove { table, key } => {
+                    txn.open_table::<(), ()>(&table)?.remove_bytes(&key)?;
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Check the integrity of the database, repairing it if needed, then
+    /// compact it to give unused space back to the file system.
+    ///
+    /// Fails if any transaction is in progress.
+    pub fn vacuum(&mut self) -> Result<VacuumReport, redb::Error> {
+        if self.read_only {
+            return Err(redb::Error::Io(read_only_error()));
+        }
+
+        let file_size = |path: &Path| std::fs::metadata(path).map(|m| m.len());
+        let before = self
+            .path
+            .as_deref()
+            .map(file_size)
+            .transpose()
+            .map_err(redb::Error::Io)?;
+
+        let repaired = !self.inner.check_integrity()?;
+        let compacted = self.inner.compact()?;
+
+        let after = self
+            .path
+            .as_deref()
+            .map(file_size)
+            .transpose()
+            .map_err(redb::Error::Io)?;
+        Ok(VacuumReport {
+            repaired,
+            compacted,
+            reclaimed_bytes: before.zip(after).map(|(b, a)| b.saturating_sub(a)),
+        })
+    }
+
+    /// Take a snapshot of a table in its own read transaction, which can be
+    /// cloned cheaply and shared across threads. See [`TableSnapshot`] for the
+    /// space it keeps from being reclaimed. The table must use the default
+    /// [`Bincode`](crate::Bincode) encoding.
+    pub fn table_snapshot<K, V>(&self, name: &str) -> Result<TableSnapshot<K, V>, redb::Error>
+    where
+        K: Encode + Decode,
+        V: Encode + Decode,
+    {
+        let txn = self.begin_read()?;
+        let table = txn.open_table::<K, V>(name)?;
+        Ok(TableSnapshot::new(txn, table))
+    }
+
+    /// Copy every row of every table of `other` into this database, in a
+    /// single write transaction, with `conflict` deciding about rows whose
+    /// key has another value here. Rows are copied as encoded, so tables of
+    /// any [`Encoding`](crate::Encoding) are merged alike.
+    ///
+    /// The crate's own metadata of `other` is not copied: type tags and
+    /// generations stay those of this database, and `.bloom` sidecars are
+    /// left to be rebuilt, since the merged rows change their tables. The
+    /// `.seq` sidecar of a log keeps the later of both next sequence numbers,
+    /// whatever `conflict` says, so appends never reuse a merged entry's.
+    /// Returns the number of rows written.
+    pub fn merge_from(
+        &self,
+        other: &Database,
+        conflict: ConflictPolicy,
+    ) -> Result<u64, redb::Error> {
+        let src = other.begin_read()?;
+        let txn = self.begin_write()?;
+        let mut merged = 0;
+        for handle in src.list_tables()? {
+            let name = handle.name();
+            if name.starts_with("redb_bincode.") || name.ends_with(".bloom") {
+                continue;
+            }
+            let is_seq = name.ends_with(".seq");
+            let from = src.open_untyped_table(name)?;
+            // Only raw bytes go through the table, its types are unused.
+            let mut to = txn.open_table::<(), ()>(name)?;
+            for entry in from.iter_raw()? {
+                let (key, value) = entry?;
+                let keep = match to.inner.get(key.as_slice())? {
+                    None => false,
+                    Some(existing) if existing.value() == value.as_slice() => true,
+                    Some(existing) if is_seq => {
+                        decode_value::<u64>(existing.value())? >= decode_value::<u64>(&value)?
+                    }
+                    Some(_) => match conflict {
+                        ConflictPolicy::KeepExisting => true,
+                        ConflictPolicy::Overwrite => false,
+                        ConflictPolicy::Error => {
+                            return Err(redb::Error::Io(std::io::Error::new(
+                                std::io::ErrorKind::AlreadyExists,
+                                format!("conflicting row in table {name}"),
+                            )));
+                        }
+                    },
+                };
+                if !keep {
+                    to.insert_bytes(&key, &value)?;
+                    merged += 1;
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(merged)
+    }
+
+    /// Move the entry of `key` from one table to another, in a single write
+    /// transaction. Returns the moved value, or `None` if `from` had no such
+    /// key, in which case nothing changes. Both tables must use the default
+    /// [`Bincode`](crate::Bincode) encoding.
+    pub fn move_row<K, V>(&self, from: &str, to: &str, key: &K) -> Result<Option<V>, redb::Error>
+    where
+        K: Encode + Decode,
+        V: Encode + Decode,
+    {
+        let txn = self.begin_write()?;
+        let moved = {
+            let mut from = txn.open_table::<K, V>(from)?;
+            let mut to = txn.open_table::<K, V>(to)?;
+
+            let key = from.encode_key(key);
+            let value = from
+                .remove_bytes(key.as_bytes())?
+                .map(|v| v.inner.value().to_vec());
+            match value {
+                Some(value) => {
+                    to.insert_bytes(key.as_bytes(), &value)?;
+                    Some(decode_value(&value)?)
+                }
+                None => None,
+            }
+        };
+        txn.commit()?;
+        Ok(moved)
+    }
+
+    /// Copy the entries of a key range from one table to another, in a single
+    /// write transaction, and remove them from `src` if `remove_from_src`.
+    /// Entries are copied as encoded, without decoding them, but `range` is
+    /// encoded with [`KEY_CONFIG`], so both tables must use the default
+    /// encoding.
+    /// Returns the number of copied entries.
+    pub fn copy_range<K, V, R>(
+        &self,
+        src: &str,
+        dst: &str,
+        range: R,
+        remove_from_src: bool,
+    ) -> Result<u64, redb::Error>
+    where
+        K: Encode + Decode,
+        V: Encode + Decode,
+        R: RangeBounds<K>,
+    {
+        let range = EncodedRange::new(&range, KEY_CONFIG);
+        let txn = self.begin_write()?;
+        let count = {
+            let mut src = txn.open_table::<K, V>(src)?;
+            let mut dst = txn.open_table::<K, V>(dst)?;
+
+            let mut count = 0;
+            if remove_from_src {
+                for entry in src
+                    .inner
+                    .extract_from_if::<&[u8], _>(range.as_bytes(), |_, _| true)?
+                {
+                    let (key, value) = entry?;
+                    src.changes.record(key.value(), ChangeKind::Remove);
+                    dst.insert_bytes(key.value(), value.value())?;
+                    count += 1;
+                }
+            } else {
+                for entry in src.inner.range::<&[u8]>(range.as_bytes())? {
+                    let (key, value) = entry?;
+                    dst.insert_bytes(key.value(), value.value())?;
+                    count += 1;
+                }
+            }
+            count
+        };
+        txn.commit()?;
+        Ok(count)
+    }
+
+    /// Re-encode every value of a table from `old_cfg` to `new_cfg`, in a
+    /// single write transaction. Keys are left untouched.
+    /// Returns the number of re-encoded entries.
+    pub fn reencode_table<K, V>(
+        &self,
+        name: &str,
+        old_cfg: impl bincode::config::Config,
+        new_cfg: impl bincode::config::Config,
+    ) -> Result<u64, redb::Error>
+    where
+        K: Encode + Decode,
+        V: Encode + Decode,
+    {
+        let txn = self.begin_write()?;
+        let count = {
+            let mut table = txn.open_table::<K, V>(name)?;
+
+            let mut entries = Vec::new();
+            for entry in table.as_raw().iter()? {
+                let (key, value) = entry?;
+                let (value, _): (V, usize) = bincode::decode_from_slice(value.value(), old_cfg)
+                    .map_err(|e| {
+                        redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+                    })?;
+                let value = bincode::encode_to_vec(value, new_cfg).expect("encoding can't fail");
+                entries.push((key.value().to_vec(), value));
+            }
+
+            for (key, value) in &entries {
+                table.insert_bytes(key, value)?;
+            }
+            entries.len() as u64
+        };
+        txn.commit()?;
+        Ok(count)
+    }
+
+    /// Make everything committed so far durable on disk, such as before
+    /// signaling another process that reads the file.
+    ///
+    /// redb doesn't expose a raw fsync, so this commits an empty transaction
+    /// with [`Durability::Immediate`](crate::Durability::Immediate), which
+    /// syncs the file. This also makes preceding commits that were made with
+    /// a weaker durability durable.
+    pub fn sync(&self) -> Result<(), redb::Error> {
+        let mut txn = self.begin_write()?;
+        txn.set_durability(crate::Durability::Immediate);
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Roll the whole database back to a persistent savepoint, see
+    /// [`WriteTransaction::persistent_savepoint`]. The savepoint is kept, so
+    /// it can be restored again.
+    ///
+    /// redb 2 can't read the database as it was at a savepoint without
+    /// restoring it, and restoring invalidates the savepoints created after
+    /// it.
+    pub fn restore_from_savepoint(&self, id: u64) -> Result<(), redb::Error> {
+        let mut txn = self.begin_write()?;
+        txn.restore_persistent_savepoint(id)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Compare two tables with the same key and value types.
+    /// Values are compared by their encoded bytes, in a single pass over both
+    /// tables, and decoded with the default [`Bincode`](crate::Bincode)
+    /// encoding.
+    pub fn diff_tables<K, V>(&self, a: &str, b: &str) -> Result<TableDiff<K, V>, redb::Error>
+    where
+        K: Encode + Decode,
+        V: Encode + Decode,
+    {
+        let txn = self.begin_read()?;
+        let a = txn.open_table::<K, V>(a)?;
+        let b = txn.open_table::<K, V>(b)?;
+
+        let mut diff = TableDiff {
+            only_in_a: vec![],
+            only_in_b: vec![],
+            changed: vec![],
+        };
+
+        let mut a_iter = a.as_raw().iter()?;
+        let mut b_iter = b.as_raw().iter()?;
+        let mut next_a = a_iter.next().transpose()?;
+        let mut next_b = b_iter.next().transpose()?;
+        loop {
+            match (&next_a, &next_b) {
+                (None, None) => break,
+                (Some((key, value)), None) => {
+                    diff.only_in_a
+                        .push((decode_key(key.value())?, decode_value(value.value())?));
+                    next_a = a_iter.next().transpose()?;
+                }
+                (None, Some((key, value))) => {
+                    diff.only_in_b
+                        .push((decode_key(key.value())?, decode_value(value.value())?));
+                    next_b = b_iter.next().transpose()?;
+                }
+                (Some((key_a, value_a)), Some((key_b, value_b))) => {
+                    match Lexicographical::compare(key_a.value(), key_b.value()) {
+                        Ordering::Less => {
+                            diff.only_in_a
+                                .push((decode_key(key_a.value())?, decode_value(value_a.value())?));
+                            next_a = a_iter.next().transpose()?;
+                        }
+                        Ordering::Greater => {
+                            diff.only_in_b
+                                .push((decode_key(key_b.value())?, decode_value(value_b.value())?));
+                            next_b = b_iter.next().transpose()?;
+                        }
+                        Ordering::Equal => {
+                            if value_a.value() != value_b.value() {
+                                diff.changed.push((
+                                    decode_key(key_a.value())?,
+                                    decode_value(value_a.value())?,
+                                    decode_value(value_b.value())?,
+                                ));
+                            }
+                            next_a = a_iter.next().transpose()?;
+                            next_b = b_iter.next().transpose()?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Join two tables on their keys, yielding the values of each key present
+    /// in both, in sort order. Since both tables are sorted by the encoded
+    /// key, this is a single pass over both, in one read transaction.
+    /// Both tables must use the default [`Bincode`](crate::Bincode) encoding.
+    pub fn join<K, VA, VB>(
+        &self,
+        a: &str,
+        b: &str,
+    ) -> Result<impl Iterator<Item = Result<(K, VA, VB), redb::Error>>, redb::Error>
+    where
+        K: Encode + Decode,
+        VA: Encode + Decode,
+        VB: Encode + Decode,
+    {
+        let txn = self.begin_read()?;
+        let mut a_iter = txn.open_table::<K, VA>(a)?.as_raw().range::<&[u8]>(..)?;
+        let mut b_iter = txn.open_table::<K, VB>(b)?.as_raw().range::<&[u8]>(..)?;
+
+        Ok(std::iter::from_fn(move || {
+            let mut next = || -> Result<Option<(K, VA, VB)>, redb::Error> {
+                let Some(mut a) = a_iter.next().transpose()? else {
+                    return Ok(None);
+                };
+                let Some(mut b) = b_iter.next().transpose()? else {
+                    return Ok(None);
+                };
+                loop {
+                    match Lexicographical::compare(a.0.value(), b.0.value()) {
+                        Ordering::Less => match a_iter.next().transpose()? {
+                            Some(entry) => a = entry,
+                            None => return Ok(None),
+                        },
+                        Ordering::Greater => match b_iter.next().transpose()? {
+                            Some(entry) => b = entry,
+                            None => return Ok(None),
+                        },
+                        Ordering::Equal => {
+                            return Ok(Some((
+                                decode_key(a.0.value())?,
+                                decode_value(a.1.value())?,
+                                decode_value(b.1.value())?,
+                            )))
+                        }
+                    }
+                }
+            };
+            next().transpose()
+        }))
+    }
+
     /// Start a read transaction.
     pub fn begin_read(&self) -> Result<tx::ReadTransaction, TransactionError> {
-        Ok(ReadTransaction::from(self.0.begin_read()?))
+        Ok(tx::ReadTransaction::from(self.inner.begin_read()?))
     }
 
     /// Start a write transaction.
+    ///
+    /// redb allows a single write transaction at a time: this blocks until any
+    /// other write transaction has committed or aborted. Write transactions
+    /// therefore never conflict, and there is no conflict error to retry on.
     pub fn begin_write(&self) -> Result<tx::WriteTransaction, TransactionError> {
-        Ok(WriteTransaction::from(self.0.begin_write()?))
+        if self.read_only {
+            return Err(TransactionError::Storage(redb::StorageError::Io(
+                read_only_error(),
+            )));
+        }
+        Ok(WriteTransaction::new(
+            self.inner.begin_write()?,
+            self.subscribers.clone(),
+        ))
+    }
+
+    /// Run transactions that are retried up to `attempts` times in all on
+    /// transient I/O errors, such as on network file systems. See
+    /// [`Retrying`] for the errors that are retried.
+    pub fn with_retry(&self, attempts: usize, backoff: Duration) -> Retrying<'_> {
+        Retrying::new(self, attempts, backoff)
+    }
+
+    /// Start a write transaction that is aborted unless it is explicitly
+    /// committed, see [`WriteGuard`](tx::WriteGuard).
+    pub fn write_guard(&self) -> Result<tx::WriteGuard, TransactionError> {
+        Ok(tx::WriteGuard::new(
+            self.begin_write()?,
+            self.abandoned_write_guards.clone(),
+        ))
+    }
+
+    /// The number of [`WriteGuard`](tx::WriteGuard)s dropped without being
+    /// committed or aborted, other than by a panic, since the database was
+    /// opened. Each of them aborted its transaction, so a growing count
+    /// usually means that an early return skipped a commit.
+    pub fn abandoned_write_guards(&self) -> u64 {
+        self.abandoned_write_guards
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Subscribe to changes of a table.
+    /// Every committed insert or remove of a key in `table` is sent to the
+    /// returned receiver. Write transactions that opened the table before
+    /// subscribing are not observed.
+    pub fn subscribe(&self, table: &str) -> Receiver<ChangeEvent> {
+        self.subscribers.subscribe(table)
     }
 }
 
 impl From<redb::Database> for Database {
     fn from(value: redb::Database) -> Self {
-        Self(value)
+        Self {
+            inner: value,
+            subscribers: Subscribers::default(),
+            read_only: false,
+            abandoned_write_guards: Arc::default(),
+            path: None,
+        }
     }
 }
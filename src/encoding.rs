@@ -0,0 +1,95 @@
+use std::marker::PhantomData;
+
+use bincode::config::{BigEndian, Config, Configuration, Fixint, LittleEndian, Varint};
+
+/// The bincode configurations a table encodes its keys and values with.
+///
+/// Keys are always big-endian, so that unsigned integers sort numerically
+/// by their encoded bytes. Enum keys sort by the index of their variant, and
+/// need a stable one, see [`assert_stable_encoding`](crate::assert_stable_encoding).
+pub trait Encoding {
+    type KeyConfig: Config;
+    type ValueConfig: Config;
+
+    const KEY: Self::KeyConfig;
+    const VALUE: Self::ValueConfig;
+
+    /// [`Encoding::VALUE`] limited to decoding at most `N` bytes, including
+    /// the memory that collections claim from their encoded length before
+    /// they are read.
+    fn value_limited<const N: usize>() -> impl Config;
+}
+
+/// Encodes keys with big-endian `I` integers and values with `E`-endian `I`
+/// integers, where `E` is [`BigEndian`] or [`LittleEndian`] and `I` is
+/// [`Varint`] or [`Fixint`].
+///
+/// The default is [`KEY_CONFIG`](crate::KEY_CONFIG) and
+/// [`VALUE_CONFIG`](crate::VALUE_CONFIG), which every table used before
+/// encodings could be picked. Tables written with one encoding can't be read
+/// with another.
+pub struct Bincode<E = BigEndian, I = Varint>(PhantomData<(E, I)>);
+
+macro_rules! impl_bincode_encoding {
+    ($endian:ident, $with_endian:ident, $int:ident, $with_int:ident) => {
+        impl Encoding for Bincode<$endian, $int> {
+            type KeyConfig = Configuration<BigEndian, $int>;
+            type ValueConfig = Configuration<$endian, $int>;
+
+            const KEY: Self::KeyConfig = bincode::config::standard().with_big_endian().$with_int();
+            const VALUE: Self::ValueConfig = bincode::config::standard().$with_endian().$with_int();
+
+            fn value_limited<const N: usize>() -> impl Config {
+                Self::VALUE.with_limit::<N>()
+            }
+        }
+    };
+}
+
+impl_bincode_encoding!(
+    BigEndian,
+    with_big_endian,
+    Varint,
+    with_variable_int_encoding
+);
+impl_bincode_encoding!(BigEndian, with_big_endian, Fixint, with_fixed_int_encoding);
+impl_bincode_encoding!(
+    LittleEndian,
+    with_little_endian,
+    Varint,
+    with_variable_int_encoding
+);
+impl_bincode_encoding!(
+    LittleEndian,
+    with_little_endian,
+    Fixint,
+    with_fixed_int_encoding
+);
+
+/// Encodes keys with big-endian fixed-width integers, and values like
+/// [`VALUE_CONFIG`](crate::VALUE_CONFIG).
+///
+/// Every integer of a key then takes its full width, so an integer key is
+/// encoded exactly as `to_be_bytes` and all keys of such a type have the
+/// same size, at the cost of larger keys than with variable-width integers.
+///
+/// With the [`Lexicographical`](crate::Lexicographical) sort order, keys of
+/// unsigned integers then sort numerically. Signed integers are encoded in
+/// two's complement, so negative keys sort after all positive ones: flip the
+/// sign bit to keep them in order, such as by storing an `i64` key as
+/// `(key as u64) ^ (1 << 63)`.
+pub struct Ordered;
+
+impl Encoding for Ordered {
+    type KeyConfig = Configuration<BigEndian, Fixint>;
+    type ValueConfig = <Bincode as Encoding>::ValueConfig;
+
+    const KEY: Self::KeyConfig = bincode::config::standard()
+        .with_big_endian()
+        .with_fixed_int_encoding();
+    const VALUE: Self::ValueConfig = <Bincode as Encoding>::VALUE;
+
+    fn value_limited<const N: usize>() -> impl Config {
+        Self::VALUE.with_limit::<N>()
+    }
+}
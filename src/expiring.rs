@@ -0,0 +1,127 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use redb::StorageError;
+
+use super::{
+    with_encode_key_buf, with_encode_value_buf, ChangeKind, SortOrder, Table, KEY_CONFIG,
+    VALUE_CONFIG,
+};
+
+/// A mutable table whose entries carry an expiry time.
+///
+/// Values are stored as `(expiry_unix_secs, V)`. Entries whose expiry is at or
+/// before `now` are treated as absent by [`ExpiringTable::get`] and removed by
+/// [`ExpiringTable::purge_expired`].
+///
+/// Only tables of the default [`Bincode`](crate::Bincode) encoding are
+/// supported.
+pub struct ExpiringTable<'txn, K, V, S>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    inner: Table<'txn, K, (u64, V), S>,
+}
+
+impl<'txn, K, V, S> From<Table<'txn, K, (u64, V), S>> for ExpiringTable<'txn, K, V, S>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    fn from(inner: Table<'txn, K, (u64, V), S>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'txn, K, V, S> ExpiringTable<'txn, K, V, S>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+{
+    /// Returns the underlying table of `(expiry, value)` pairs.
+    pub fn as_table(&self) -> &Table<'txn, K, (u64, V), S> {
+        &self.inner
+    }
+
+    /// Get a value from the table by key.
+    /// Returns `None` if the entry is missing or expired at `now`.
+    pub fn get<Q>(&self, key: &Q, now: u64) -> Result<Option<V>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        let Some(guard) = self.inner.get(key)? else {
+            return Ok(None);
+        };
+        let (expiry, value) = guard.value().map_err(|e| {
+            redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })?;
+
+        Ok((expiry > now).then_some(value))
+    }
+
+    /// Inserts a key and value into the table, expiring at `expires_at`
+    /// (unix seconds).
+    pub fn insert<KQ, VQ>(
+        &mut self,
+        key: &KQ,
+        value: &VQ,
+        expires_at: u64,
+    ) -> Result<(), StorageError>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KQ: bincode::Encode + ?Sized,
+        VQ: bincode::Encode + ?Sized,
+    {
+        unsafe {
+            with_encode_key_buf(|key_buf| {
+                let key_size = bincode::encode_into_std_write(key, key_buf, KEY_CONFIG)
+                    .expect("encoding can't fail");
+
+                with_encode_value_buf(|value_buf| {
+                    let value_size = bincode::encode_into_std_write(
+                        (expires_at, value),
+                        value_buf,
+                        VALUE_CONFIG,
+                    )
+                    .expect("encoding can't fail");
+
+                    self.inner
+                        .insert_bytes(&key_buf[..key_size], &value_buf[..value_size])?;
+                    Ok(())
+                })
+            })
+        }
+    }
+
+    /// Remove a value from the table by key, whether or not it has expired.
+    /// Returns the expiry and value that were removed, if any.
+    pub fn remove<KQ>(&mut self, key: &KQ) -> Result<Option<(u64, V)>, redb::Error>
+    where
+        K: Borrow<KQ>,
+        KQ: bincode::Encode + ?Sized,
+    {
+        self.inner
+            .remove(key)?
+            .map(|v| v.value())
+            .transpose()
+            .map_err(|e| redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+    }
+
+    /// Remove all entries that have expired at `now`.
+    /// Returns the number of removed entries.
+    pub fn purge_expired(&mut self, now: u64) -> Result<u64, redb::Error> {
+        let mut purged = 0;
+        // Only the leading expiry is decoded; the value itself is never touched.
+        for entry in self.inner.inner.extract_if(|_, value| {
+            bincode::decode_from_slice::<u64, _>(value, VALUE_CONFIG)
+                .is_ok_and(|(expiry, _)| expiry <= now)
+        })? {
+            let (key, _) = entry?;
+            self.inner.changes.record(key.value(), ChangeKind::Remove);
+            purged += 1;
+        }
+        Ok(purged)
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{decode_value, Database, KEY_CONFIG, VALUE_CONFIG};
+
+/// Reusable encode buffers shared by the clones of a [`DatabaseHandle`].
+#[derive(Default)]
+struct BufferPool(Mutex<Vec<Vec<u8>>>);
+
+impl BufferPool {
+    /// Maximum number of idle buffers kept for reuse.
+    const MAX_IDLE: usize = 64;
+
+    fn take(&self) -> Vec<u8> {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop()
+            .unwrap_or_default()
+    }
+
+    fn give(&self, mut buf: Vec<u8>) {
+        buf.clear();
+        let mut idle = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if idle.len() < Self::MAX_IDLE {
+            idle.push(buf);
+        }
+    }
+}
+
+/// A cloneable handle to a [`Database`] whose operations encode into buffers
+/// from a shared pool, rather than into the thread-local buffers that table
+/// methods use. On thread pools where tasks move between threads, this keeps
+/// the number of buffers bounded by the number of operations in flight
+/// instead of by the number of threads.
+///
+/// Each operation runs in its own transaction, on tables of the default
+/// [`Bincode`](crate::Bincode) encoding.
+#[derive(Clone)]
+pub struct DatabaseHandle {
+    db: Arc<Database>,
+    buffers: Arc<BufferPool>,
+}
+
+impl From<Database> for DatabaseHandle {
+    fn from(value: Database) -> Self {
+        Self::from(Arc::new(value))
+    }
+}
+
+impl From<Arc<Database>> for DatabaseHandle {
+    fn from(db: Arc<Database>) -> Self {
+        Self {
+            db,
+            buffers: Arc::default(),
+        }
+    }
+}
+
+impl DatabaseHandle {
+    /// Returns the underlying database.
+    pub fn database(&self) -> &Arc<Database> {
+        &self.db
+    }
+
+    /// Runs `f` with a buffer from the pool holding `value` encoded with
+    /// `config`.
+    fn with_encoded<T, R>(
+        &self,
+        value: &T,
+        config: impl bincode::config::Config,
+        f: impl FnOnce(&[u8]) -> R,
+    ) -> R
+    where
+        T: bincode::Encode + ?Sized,
+    {
+        let mut buf = self.buffers.take();
+        bincode::encode_into_std_write(value, &mut buf, config).expect("encoding can't fail");
+        let res = f(&buf);
+        self.buffers.give(buf);
+        res
+    }
+
+    /// Get a value from a table by key.
+    pub fn get<K, V>(&self, table: &str, key: &K) -> Result<Option<V>, redb::Error>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table::<K, V>(table)?;
+        self.with_encoded(key, KEY_CONFIG, |key| {
+            table
+                .inner
+                .get(key)?
+                .map(|value| decode_value(value.value()))
+                .transpose()
+        })
+    }
+
+    /// Insert a key and value into a table.
+    pub fn insert<K, V>(&self, table: &str, key: &K, value: &V) -> Result<(), redb::Error>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        let txn = self.db.begin_write()?;
+        {
+            let mut table = txn.open_table::<K, V>(table)?;
+            self.with_encoded(key, KEY_CONFIG, |key| {
+                self.with_encoded(value, VALUE_CONFIG, |value| table.insert_bytes(key, value))
+                    .map(drop)
+            })?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Remove a key from a table.
+    /// Returns the value that was removed, if any.
+    pub fn remove<K, V>(&self, table: &str, key: &K) -> Result<Option<V>, redb::Error>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        let txn = self.db.begin_write()?;
+        let removed = {
+            let mut table = txn.open_table::<K, V>(table)?;
+            self.with_encoded(key, KEY_CONFIG, |key| {
+                table
+                    .remove_bytes(key)?
+                    .map(|value| decode_value(value.inner.value()))
+                    .transpose()
+            })?
+        };
+        txn.commit()?;
+        Ok(removed)
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+
+use redb::{ReadableTable, StorageError};
+
+use super::{decode_key, decode_value, Lexicographical, SortOrder, Table, KEY_CONFIG};
+
+/// A mutable table with a secondary index on a field of its values.
+///
+/// The index is a `(IK, K) -> ()` table, where `IK` is extracted from each
+/// value. Inserts and removals through this handle update the index in the
+/// same write transaction, so both commit or abort together. Writes that
+/// bypass this handle leave the index stale.
+///
+/// Both tables use the default [`Bincode`](crate::Bincode) encoding.
+pub struct IndexedTable<'txn, K, V, S, IK, F>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    inner: Table<'txn, K, V, S>,
+    index: Table<'txn, (IK, K), (), Lexicographical>,
+    extract: F,
+}
+
+impl<'txn, K, V, S, IK, F> IndexedTable<'txn, K, V, S, IK, F>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+    IK: bincode::Encode + bincode::Decode,
+    F: Fn(&V) -> IK,
+{
+    pub(crate) fn new(
+        inner: Table<'txn, K, V, S>,
+        index: Table<'txn, (IK, K), (), Lexicographical>,
+        extract: F,
+    ) -> Self {
+        Self {
+            inner,
+            index,
+            extract,
+        }
+    }
+
+    /// Returns the underlying table.
+    pub fn as_table(&self) -> &Table<'txn, K, V, S> {
+        &self.inner
+    }
+
+    /// Get a value from the table by key.
+    pub fn get(&self, key: &K) -> Result<Option<V>, redb::Error> {
+        self.inner
+            .get(key)?
+            .map(|v| v.value())
+            .transpose()
+            .map_err(|e| redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+    }
+
+    /// Get all entries whose value has the given index key.
+    pub fn get_by_index(&self, ik: &IK) -> Result<Vec<(K, V)>, redb::Error> {
+        let prefix = bincode::encode_to_vec(ik, KEY_CONFIG).expect("encoding can't fail");
+
+        let mut res = vec![];
+        for entry in self.index.inner.range::<&[u8]>(prefix.as_slice()..)? {
+            let (index_key, _) = entry?;
+            let Some(key) = index_key.value().strip_prefix(prefix.as_slice()) else {
+                break;
+            };
+            // The index can only point at rows written through this handle.
+            let value = self.inner.inner.get(key)?.ok_or_else(|| {
+                StorageError::Corrupted("secondary index points at a missing row".into())
+            })?;
+            res.push((decode_key(key)?, decode_value(value.value())?));
+        }
+        Ok(res)
+    }
+
+    /// Inserts a key and value into the table and index.
+    /// Returns the previous value, if any.
+    pub fn insert(&mut self, key: &K, value: &V) -> Result<Option<V>, redb::Error> {
+        let prev = self
+            .inner
+            .insert(key, value)?
+            .map(|v| v.value())
+            .transpose()
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+
+        if let Some(prev) = &prev {
+            let old = self.index_key(prev, key);
+            self.index.remove_bytes(&old)?;
+        }
+        let new = self.index_key(value, key);
+        self.index.insert_bytes(&new, &[])?;
+
+        Ok(prev)
+    }
+
+    /// Remove a value from the table and index by key.
+    /// Returns the value that was removed, if any.
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>, redb::Error> {
+        let prev = self
+            .inner
+            .remove(key)?
+            .map(|v| v.value())
+            .transpose()
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+
+        if let Some(prev) = &prev {
+            let old = self.index_key(prev, key);
+            self.index.remove_bytes(&old)?;
+        }
+
+        Ok(prev)
+    }
+
+    fn index_key(&self, value: &V, key: &K) -> Vec<u8> {
+        bincode::encode_to_vec(((self.extract)(value), key), KEY_CONFIG)
+            .expect("encoding can't fail")
+    }
+}
@@ -2,13 +2,28 @@ use std::borrow::Borrow;
 use std::cell::UnsafeCell;
 use std::fmt;
 use std::marker::PhantomData;
+use std::ops::{Bound, ControlFlow, RangeBounds};
+pub use redb::Durability;
 pub use redb::StorageError;
+pub use redb::TableError;
 use redb::{ReadableTable, ReadableTableMetadata, TableHandle};
 
-pub const BINCODE_CONFIG: bincode::config::Configuration<bincode::config::BigEndian> =
-    bincode::config::standard()
-        .with_big_endian()
-        .with_variable_int_encoding();
+/// The encoding of keys of the default [`Bincode`] encoding.
+///
+/// Keys are compared by their encoded bytes, so they are big-endian: with
+/// variable-int encoding, unsigned integers still sort numerically, but
+/// signed integers (zigzag encoded) and anything length-prefixed, such as
+/// strings, sort by their encoding rather than their `Ord`.
+pub const KEY_CONFIG: <Bincode as Encoding>::KeyConfig = <Bincode as Encoding>::KEY;
+
+/// The encoding of values of the default [`Bincode`] encoding. Values take
+/// no part in ordering, so an [`Encoding`] can tune them independently of
+/// keys.
+pub const VALUE_CONFIG: <Bincode as Encoding>::ValueConfig = <Bincode as Encoding>::VALUE;
+
+/// The encoding of both keys and values, before they could differ.
+#[deprecated(note = "use `KEY_CONFIG` or `VALUE_CONFIG`")]
+pub const BINCODE_CONFIG: bincode::config::Configuration<bincode::config::BigEndian> = KEY_CONFIG;
 
 thread_local! {
     pub static ENCODE_KEY: std::cell::UnsafeCell<Vec<u8>> = const { std::cell::UnsafeCell::new(vec![]) };
@@ -44,9 +59,233 @@ unsafe fn with_encode_value_buf<R>(f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
     })
 }
 
+/// Decodes a key, reporting failures as an invalid-data I/O error.
+fn decode_key<T: bincode::Decode>(data: &[u8]) -> Result<T, redb::Error> {
+    decode_key_with::<Bincode, T>(data)
+}
+
+/// Decodes a value, reporting failures as an invalid-data I/O error.
+fn decode_value<T: bincode::Decode>(data: &[u8]) -> Result<T, redb::Error> {
+    decode_value_with::<Bincode, T>(data)
+}
+
+/// Decodes a key encoded with `C`.
+fn decode_key_with<C: Encoding, T: bincode::Decode>(data: &[u8]) -> Result<T, redb::Error> {
+    bincode::decode_from_slice(data, C::KEY)
+        .map(|v| v.0)
+        .map_err(|e| redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+}
+
+/// Decodes a value encoded with `C`.
+fn decode_value_with<C: Encoding, T: bincode::Decode>(data: &[u8]) -> Result<T, redb::Error> {
+    bincode::decode_from_slice(data, C::VALUE)
+        .map(|v| v.0)
+        .map_err(|e| redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+}
+
+/// Rejects values whose encoded size is over `limit`, see
+/// [`ReadTransaction::open_table_limited`].
+fn check_limit(data: &[u8], limit: Option<usize>) -> Result<(), bincode::error::DecodeError> {
+    match limit {
+        Some(limit) if data.len() > limit => Err(bincode::error::DecodeError::LimitExceeded),
+        _ => Ok(()),
+    }
+}
+
+/// Evaluates `$decode` with `$config` bound to [`Encoding::value_limited`] of
+/// `$limit` rounded up to a power of two, since bincode only takes limits as
+/// const generics. Limits up to 4 KiB are rounded up to 4 KiB, and limits over
+/// 2 GiB don't bound the decoded size.
+macro_rules! with_value_limit {
+    ($c:ty, $limit:expr, |$config:ident| $decode:expr) => {
+        with_value_limit!(@arms $c, $limit, $config, $decode;
+            13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31)
+    };
+    (@arms $c:ty, $limit:expr, $config:ident, $decode:expr; $($shift:literal)*) => {
+        match $limit.checked_next_power_of_two().map_or(usize::BITS, usize::trailing_zeros) {
+            0..=12 => {
+                let $config = <$c>::value_limited::<{ 1 << 12 }>();
+                $decode
+            }
+            $($shift => {
+                let $config = <$c>::value_limited::<{ 1 << $shift }>();
+                $decode
+            })*
+            _ => {
+                let $config = <$c>::VALUE;
+                $decode
+            }
+        }
+    };
+}
+
+/// Decodes a value with `C`, unless it is over `limit`, see
+/// [`ReadTransaction::open_table_limited`]. The encoded size is checked first,
+/// then decoding fails before allocating more than about `limit` bytes.
+fn decode_value_checked<C: Encoding, T: bincode::Decode>(
+    data: &[u8],
+    limit: Option<usize>,
+) -> Result<T, bincode::error::DecodeError> {
+    check_limit(data, limit)?;
+    match limit {
+        Some(limit) => {
+            with_value_limit!(C, limit, |config| bincode::decode_from_slice(data, config))
+        }
+        None => bincode::decode_from_slice(data, C::VALUE),
+    }
+    .map(|v| v.0)
+}
+
+/// Like [`decode_value_checked`], borrowing from `data`.
+fn borrow_decode_value_checked<'b, C: Encoding, T: bincode::BorrowDecode<'b>>(
+    data: &'b [u8],
+    limit: Option<usize>,
+) -> Result<T, bincode::error::DecodeError> {
+    check_limit(data, limit)?;
+    match limit {
+        Some(limit) => with_value_limit!(C, limit, |config| bincode::borrow_decode_from_slice(
+            data, config
+        )),
+        None => bincode::borrow_decode_from_slice(data, C::VALUE),
+    }
+    .map(|v| v.0)
+}
+
+/// Decoded entries of a table, in sort order.
+pub type Entries<K, V> = Vec<(K, V)>;
+
+/// An encoded key and value.
+pub type RawEntry = (Vec<u8>, Vec<u8>);
+
+/// A group key with its entries, see [`ReadOnlyTable::group_by_prefix`].
+pub type Group<P, K, V> = (P, Entries<K, V>);
+
+/// Encoded values stored under more than one key, each with its keys, see
+/// [`ReadOnlyTable::find_duplicate_values`].
+pub type DuplicateValues<K> = Vec<(Vec<u8>, Vec<K>)>;
+
+/// Decodes a key and value read from a table.
+#[allow(clippy::type_complexity)]
+fn decode_entry<K, V, S, C>(
+    entry: Result<
+        (
+            redb::AccessGuard<'_, sort::SortKey<S>>,
+            redb::AccessGuard<'_, &'static [u8]>,
+        ),
+        StorageError,
+    >,
+    limit: Option<usize>,
+) -> Result<(K, V), redb::Error>
+where
+    K: bincode::Decode,
+    V: bincode::Decode,
+    S: SortOrder + fmt::Debug + 'static,
+    C: Encoding,
+{
+    let (key, value) = entry?;
+    Ok((
+        decode_key_with::<C, _>(key.value())?,
+        decode_value_checked::<C, _>(value.value(), limit).map_err(|e| {
+            redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })?,
+    ))
+}
+
+/// Advances `iter` past `n` entries without decoding them. Unlike
+/// [`Iterator::skip`], a failure to read a skipped entry is returned.
+fn skip_entries<T>(
+    iter: &mut impl Iterator<Item = Result<T, StorageError>>,
+    n: usize,
+) -> Result<(), StorageError> {
+    for entry in iter.take(n) {
+        entry?;
+    }
+    Ok(())
+}
+
+/// The encoded bounds of a range of keys.
+struct EncodedRange {
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+}
+
+impl EncodedRange {
+    fn new<Q, R>(range: &R, config: impl bincode::config::Config) -> Self
+    where
+        Q: bincode::Encode + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let encode = |key: &Q| bincode::encode_to_vec(key, config).expect("encoding can't fail");
+        Self {
+            start: range.start_bound().map(encode),
+            end: range.end_bound().map(encode),
+        }
+    }
+
+    fn as_bytes(&self) -> (Bound<&[u8]>, Bound<&[u8]>) {
+        (
+            self.start.as_ref().map(Vec::as_slice),
+            self.end.as_ref().map(Vec::as_slice),
+        )
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` it is stable across Rust releases,
+/// so it is safe for hashes that get persisted.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Reads the big-endian length prefix of an exported field.
+/// Returns `None` at a clean end of input, before any byte of the prefix.
+fn read_len(r: &mut impl std::io::Read) -> std::io::Result<Option<usize>> {
+    let mut len = [0; 8];
+    let mut read = 0;
+    while read < len.len() {
+        match r.read(&mut len[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    usize::try_from(u64::from_be_bytes(len))
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Reads a length-prefixed field written by [`ReadOnlyTable::export_to`].
+fn read_field(r: &mut impl std::io::Read) -> std::io::Result<Option<Vec<u8>>> {
+    use std::io::Read;
+
+    let Some(len) = read_len(r)? else {
+        return Ok(None);
+    };
+    // The length is untrusted, so memory is only claimed as bytes arrive.
+    let mut field = vec![];
+    r.take(len as u64).read_to_end(&mut field)?;
+    if field.len() != len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "field is shorter than its length",
+        ));
+    }
+    Ok(Some(field))
+}
+
 mod sort;
 pub use sort::*;
 
+mod encoding;
+pub use encoding::*;
+
 mod database;
 pub use database::*;
 
@@ -56,44 +295,230 @@ pub use tx::*;
 mod traits;
 pub use traits::*;
 
-pub struct AccessGuard<'a, V> {
+mod expiring;
+pub use expiring::*;
+
+mod watch;
+pub use watch::*;
+
+mod bloom;
+pub use bloom::*;
+
+mod index;
+pub use index::*;
+
+mod counters;
+pub use counters::*;
+
+mod log;
+pub use log::*;
+
+mod versioned;
+pub use versioned::*;
+
+mod schema;
+pub use schema::*;
+
+mod batch;
+pub use batch::*;
+
+mod handle;
+pub use handle::*;
+
+mod snapshot;
+pub use snapshot::*;
+
+mod untyped;
+pub use untyped::*;
+
+mod retry;
+pub use retry::*;
+
+mod stability;
+pub use stability::*;
+
+#[cfg(feature = "zstd")]
+mod compressed;
+#[cfg(feature = "zstd")]
+pub use compressed::*;
+
+#[cfg(feature = "tokio")]
+mod nonblocking;
+#[cfg(feature = "tokio")]
+pub use nonblocking::*;
+
+pub struct AccessGuard<'a, V, C = Bincode> {
     inner: redb::AccessGuard<'a, &'static [u8]>,
+    limit: Option<usize>,
     _v: PhantomData<V>,
+    _c: PhantomData<C>,
 }
 
-impl<'a, V> From<redb::AccessGuard<'a, &'_ [u8]>> for AccessGuard<'a, V> {
+impl<'a, V, C> From<redb::AccessGuard<'a, &'_ [u8]>> for AccessGuard<'a, V, C> {
     fn from(inner: redb::AccessGuard<'a, &'_ [u8]>) -> Self {
         Self {
             inner,
+            limit: None,
             _v: PhantomData,
+            _c: PhantomData,
         }
     }
 }
 
-impl<'a, V> AccessGuard<'a, V>
+impl<'a, V, C> AccessGuard<'a, V, C>
 where
     V: bincode::Decode,
+    C: Encoding,
 {
+    /// Decode the value. Fails with [`DecodeError::LimitExceeded`] if it was
+    /// read from a table opened with a size limit that it exceeds.
+    ///
+    /// [`DecodeError::LimitExceeded`]: bincode::error::DecodeError::LimitExceeded
     pub fn value(&self) -> Result<V, bincode::error::DecodeError> {
-        bincode::decode_from_slice(self.inner.value(), BINCODE_CONFIG).map(|v| v.0)
+        decode_value_checked::<C, _>(self.inner.value(), self.limit)
+    }
+
+    /// Decode the value and release the guard, and with it the borrow of the
+    /// table. [`AccessGuard::value`] returns an owned value too, so decoded
+    /// values can always outlive their guards.
+    pub fn into_value(self) -> Result<V, bincode::error::DecodeError> {
+        self.value()
+    }
+
+    /// Decode the value as `T`, borrowing from the stored bytes instead of
+    /// copying them, for types such as `&[u8]` and `&str` that bincode can
+    /// decode in place. `T` must be encoded the same way as `V`.
+    pub fn borrow_value<'b, T>(&'b self) -> Result<T, bincode::error::DecodeError>
+    where
+        T: bincode::BorrowDecode<'b>,
+    {
+        borrow_decode_value_checked::<C, _>(self.inner.value(), self.limit)
+    }
+
+    /// Returns the bytes of a value that is a byte buffer such as `Vec<u8>`,
+    /// without copying them.
+    ///
+    /// bincode encodes a byte buffer as its length followed by the bytes, so
+    /// this skips the length. Values of other types that happen to start with
+    /// a valid length, such as a `String`, are read the same way.
+    pub fn as_bytes_value(&self) -> Result<&[u8], bincode::error::DecodeError> {
+        self.borrow_value()
+    }
+}
+
+/// A key encoded once, to be reused across lookups and inserts on tables
+/// keyed by `K`.
+pub struct RawKey<K, C = Bincode> {
+    bytes: Vec<u8>,
+    _k: PhantomData<K>,
+    _c: PhantomData<C>,
+}
+
+impl<K, C: Encoding> RawKey<K, C> {
+    fn encode<Q>(key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        Self {
+            bytes: bincode::encode_to_vec(key, C::KEY).expect("encoding can't fail"),
+            _k: PhantomData,
+            _c: PhantomData,
+        }
+    }
+}
+
+impl<K, C> RawKey<K, C> {
+    /// Returns the encoded key bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// The result of [`ReadOnlyTable::validate`].
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// The number of rows whose key and value decoded.
+    pub ok: u64,
+    /// The raw keys of the rows whose key or value failed to decode.
+    pub failed: Vec<Vec<u8>>,
+}
+
+/// The encoded bytes of an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawRow {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+/// The error of [`ReadOnlyTable::try_get`].
+#[derive(Debug)]
+pub enum TryGetError {
+    Storage(StorageError),
+    /// The value is, or claims to hold, more bytes than the decode limit.
+    DecodeLimitExceeded,
+    /// The value is malformed, or of another type.
+    Decode(bincode::error::DecodeError),
+}
+
+impl fmt::Display for TryGetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryGetError::Storage(e) => e.fmt(f),
+            TryGetError::DecodeLimitExceeded => f.write_str("value exceeds the decode limit"),
+            TryGetError::Decode(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TryGetError {}
+
+impl From<StorageError> for TryGetError {
+    fn from(e: StorageError) -> Self {
+        TryGetError::Storage(e)
+    }
+}
+
+impl From<bincode::error::DecodeError> for TryGetError {
+    fn from(e: bincode::error::DecodeError) -> Self {
+        match e {
+            bincode::error::DecodeError::LimitExceeded => TryGetError::DecodeLimitExceeded,
+            e => TryGetError::Decode(e),
+        }
+    }
+}
+
+impl From<TryGetError> for redb::Error {
+    fn from(e: TryGetError) -> Self {
+        match e {
+            TryGetError::Storage(e) => e.into(),
+            e => redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        }
     }
 }
 
 /// A read-only table.
-pub struct ReadOnlyTable<K, V, S>
+///
+/// It reads from the snapshot of the [`ReadTransaction`] that opened it, and
+/// doesn't borrow the transaction, so it and its iterators can be held
+/// across concurrent write commits without seeing them.
+pub struct ReadOnlyTable<K, V, S, C = Bincode>
 where
     S: SortOrder + fmt::Debug + 'static,
 {
     inner: redb::ReadOnlyTable<sort::SortKey<S>, &'static [u8]>,
+    max_value_bytes: Option<usize>,
     _k: PhantomData<K>,
     _v: PhantomData<V>,
+    _c: PhantomData<C>,
 }
 
-impl<K, V, S> ReadOnlyTable<K, V, S>
+impl<K, V, S, C> ReadOnlyTable<K, V, S, C>
 where
     S: SortOrder + fmt::Debug + 'static,
     K: bincode::Encode + bincode::Decode,
     V: bincode::Encode + bincode::Decode,
+    C: Encoding,
 {
     /// Returns the underlying redb table.
     pub fn as_raw(&self) -> &redb::ReadOnlyTable<sort::SortKey<S>, &'static [u8]> {
@@ -101,21 +526,115 @@ where
     }
 
     /// Get a value from the table by key.
-    pub fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'static, V>>, StorageError>
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'static, V, C>>, StorageError>
     where
         K: Borrow<Q>,
         Q: bincode::Encode + ?Sized,
     {
         unsafe {
             Ok(with_encode_key_buf(|buf| {
-                let size = bincode::encode_into_std_write(key, buf, BINCODE_CONFIG)
-                    .expect("encoding can't fail");
+                let size =
+                    bincode::encode_into_std_write(key, buf, C::KEY).expect("encoding can't fail");
                 self.inner.get(&buf[..size])
             })?
-            .map(AccessGuard::from))
+            .map(|v| self.guard(v)))
         }
     }
 
+    /// Get a value from the table by key, or `V::default()` if it is absent.
+    pub fn get_or_default<Q>(&self, key: &Q) -> Result<V, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+        V: Default,
+    {
+        let Some(value) = self.get(key)? else {
+            return Ok(V::default());
+        };
+        self.decode_value_limited(value.inner.value())
+    }
+
+    /// Get only the leading fields of a value, decoded as `P`.
+    ///
+    /// bincode encodes struct fields in order, so `P` may be a struct with
+    /// the same leading fields as `V`, in the same order and of the same
+    /// types, and the trailing fields are never decoded. Nothing checks that
+    /// the layouts actually line up: if they don't, this returns garbage or a
+    /// decode error.
+    pub fn get_partial<P, Q>(&self, key: &Q) -> Result<Option<P>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+        P: bincode::Decode,
+    {
+        let Some(value) = self.get(key)? else {
+            return Ok(None);
+        };
+        Ok(Some(self.decode_value_limited(value.inner.value())?))
+    }
+
+    /// Get a value from the table by key, with the number of bytes its
+    /// encoding occupies.
+    pub fn get_with_size<Q>(&self, key: &Q) -> Result<Option<(V, usize)>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        let Some(value) = self.get(key)? else {
+            return Ok(None);
+        };
+        let data = value.inner.value();
+        Ok(Some((self.decode_value_limited(data)?, data.len())))
+    }
+
+    /// Get a value from the table by key, decoding at most `LIMIT` bytes.
+    ///
+    /// Unlike with [`ReadOnlyTable::get`], a collection whose encoded length
+    /// claims more than `LIMIT` bytes fails to decode before it allocates
+    /// them, so malformed or untrusted values can't exhaust memory. The
+    /// limit of [`ReadTransaction::open_table_limited`] applies too.
+    pub fn try_get<const LIMIT: usize, Q>(&self, key: &Q) -> Result<Option<V>, TryGetError>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        let Some(value) = self.get(key)? else {
+            return Ok(None);
+        };
+        let data = value.inner.value();
+        check_limit(data, self.max_value_bytes)?;
+        Ok(Some(
+            bincode::decode_from_slice(data, C::value_limited::<LIMIT>())?.0,
+        ))
+    }
+
+    /// Encode a key once, for use with [`ReadOnlyTable::get_raw`] and
+    /// [`Table::insert_raw`].
+    pub fn encode_key<Q>(&self, key: &Q) -> RawKey<K, C>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        RawKey::encode(key)
+    }
+
+    /// Returns whether the table contains the key. The value is not decoded.
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool, StorageError>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Get a value from the table by an already encoded key.
+    pub fn get_raw(
+        &self,
+        key: &RawKey<K, C>,
+    ) -> Result<Option<AccessGuard<'static, V, C>>, StorageError> {
+        Ok(self.inner.get(key.as_bytes())?.map(|v| self.guard(v)))
+    }
+
     /// Get a range of values from the table.
     /// The range is inclusive on the start and exclusive on the end.
     pub fn get_many(
@@ -123,7 +642,12 @@ where
         start: Option<usize>,
         end: Option<usize>,
     ) -> Result<Vec<(K, V)>, redb::Error> {
-        let mut res = vec![];
+        // The window can't be larger than the table, whose length is stored.
+        let len = usize::try_from(self.inner.len()?).unwrap_or(usize::MAX);
+        let capacity = end
+            .map_or(len, |end| end.min(len))
+            .saturating_sub(start.unwrap_or(0));
+        let mut res = Vec::with_capacity(capacity);
         let mut i = 0;
 
         let mut iter = self.inner.iter()?;
@@ -143,16 +667,12 @@ where
 
             let (key, value) = r?;
 
-            let key = bincode::decode_from_slice(key.value(), BINCODE_CONFIG)
-                .map(|v| v.0)
-                .map_err(|e| {
-                    redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
-                })?;
-            let value = bincode::decode_from_slice(value.value(), BINCODE_CONFIG)
+            let key = bincode::decode_from_slice(key.value(), C::KEY)
                 .map(|v| v.0)
                 .map_err(|e| {
                     redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
                 })?;
+            let value = self.decode_value_limited(value.value())?;
             res.push((key, value));
 
             i += 1;
@@ -160,6 +680,66 @@ where
         Ok(res)
     }
 
+    /// Get the entries of the table from position `start`, as long as their
+    /// encoded values total at most `max_bytes`, to page through the table
+    /// by payload size. Values are only decoded once they are known to fit.
+    ///
+    /// An entry whose value alone is larger than `max_bytes` is never
+    /// returned: the result is empty when it comes first.
+    pub fn get_until_bytes(
+        &self,
+        start: Option<usize>,
+        max_bytes: usize,
+    ) -> Result<Vec<(K, V)>, redb::Error> {
+        let mut res = vec![];
+        let mut total = 0usize;
+        let mut iter = self.inner.iter()?;
+        skip_entries(&mut iter, start.unwrap_or(0))?;
+        for entry in iter {
+            let (key, value) = entry?;
+            total = total.saturating_add(value.value().len());
+            if total > max_bytes {
+                break;
+            }
+            res.push((
+                decode_key_with::<C, _>(key.value())?,
+                self.decode_value_limited(value.value())?,
+            ));
+        }
+        Ok(res)
+    }
+
+    /// Like [`ReadOnlyTable::get_many`], but entries that fail to decode
+    /// don't abort the scan: they are returned as raw bytes next to the
+    /// decoded entries.
+    pub fn get_many_lenient(
+        &self,
+        start: Option<usize>,
+        end: Option<usize>,
+    ) -> Result<(Entries<K, V>, Vec<RawRow>), redb::Error> {
+        let start = start.unwrap_or(0);
+        let len = end.map_or(usize::MAX, |end| end.saturating_sub(start));
+
+        let mut res = vec![];
+        let mut failed = vec![];
+        let mut iter = self.inner.iter()?;
+        skip_entries(&mut iter, start)?;
+        for entry in iter.take(len) {
+            let (key, value) = entry?;
+            match (
+                decode_key_with::<C, K>(key.value()),
+                self.decode_value_limited::<V>(value.value()),
+            ) {
+                (Ok(key), Ok(value)) => res.push((key, value)),
+                _ => failed.push(RawRow {
+                    key: key.value().to_vec(),
+                    value: value.value().to_vec(),
+                }),
+            }
+        }
+        Ok((res, failed))
+    }
+
     pub fn get_many_where<'a, F>(
         &self,
         start: Option<usize>,
@@ -189,16 +769,12 @@ where
 
             let (key, value) = r?;
 
-            let key = bincode::decode_from_slice(key.value(), BINCODE_CONFIG)
-                .map(|v| v.0)
-                .map_err(|e| {
-                    redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
-                })?;
-            let value = bincode::decode_from_slice(value.value(), BINCODE_CONFIG)
+            let key = bincode::decode_from_slice(key.value(), C::KEY)
                 .map(|v| v.0)
                 .map_err(|e| {
                     redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
                 })?;
+            let value = self.decode_value_limited(value.value())?;
 
             if f((&key, &value)) {
                 res.push((key, value));
@@ -209,100 +785,1158 @@ where
         Ok(res)
     }
 
-    /// Get metadata about the table.
-    pub fn stats(&self) -> Result<redb::TableStats, redb::StorageError> {
-        self.inner.stats()
+    /// Get all entries whose value matches the given predicate.
+    /// Keys are only decoded for matching entries.
+    pub fn get_where_value<F>(&self, mut f: F) -> Result<Vec<(K, V)>, redb::Error>
+    where
+        F: FnMut(&V) -> bool,
+    {
+        let mut res = vec![];
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            let value = self.decode_value_limited(value.value())?;
+            if f(&value) {
+                res.push((decode_key_with::<C, _>(key.value())?, value));
+            }
+        }
+        Ok(res)
     }
-}
-
-/// A mutable table in the database.
-pub struct Table<'txn, K, V, S>
-where
-    S: SortOrder + fmt::Debug + 'static,
-{
-    inner: redb::Table<'txn, sort::SortKey<S>, &'static [u8]>,
-    _k: PhantomData<K>,
-    _v: PhantomData<V>,
-}
 
+    /// Get the entry whose value has the largest `f(value)`, the first one in
+    /// key order on ties. Returns `None` if the table is empty.
+    pub fn max_by<B, F>(&self, f: F) -> Result<Option<(K, V)>, redb::Error>
+    where
+        B: Ord,
+        F: FnMut(&V) -> B,
+    {
+        self.extremum_by(f, std::cmp::Ordering::Greater)
+    }
 
+    /// Get the entry whose value has the smallest `f(value)`, the first one
+    /// in key order on ties. Returns `None` if the table is empty.
+    pub fn min_by<B, F>(&self, f: F) -> Result<Option<(K, V)>, redb::Error>
+    where
+        B: Ord,
+        F: FnMut(&V) -> B,
+    {
+        self.extremum_by(f, std::cmp::Ordering::Less)
+    }
 
+    /// Scans for the first entry whose `f(value)` compares as `wanted`
+    /// against all others. Keys are only decoded for the result.
+    fn extremum_by<B, F>(
+        &self,
+        mut f: F,
+        wanted: std::cmp::Ordering,
+    ) -> Result<Option<(K, V)>, redb::Error>
+    where
+        B: Ord,
+        F: FnMut(&V) -> B,
+    {
+        let mut best: Option<(B, Vec<u8>, V)> = None;
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            let value = self.decode_value_limited(value.value())?;
+            let b = f(&value);
+            if best.as_ref().is_none_or(|(best, ..)| b.cmp(best) == wanted) {
+                best = Some((b, key.value().to_vec(), value));
+            }
+        }
+        best.map(|(_, key, value)| Ok((decode_key_with::<C, _>(&key)?, value)))
+            .transpose()
+    }
 
-impl<'txn, K, V, S> Table<'txn, K, V, S>
-where
-    S: SortOrder + fmt::Debug + 'static,
-    K: bincode::Encode + bincode::Decode,
-    V: bincode::Encode + bincode::Decode,
-{
-    pub fn as_raw(&self) -> &redb::Table<sort::SortKey<S>, &'static [u8]> {
-        &self.inner
+    /// Iterate over the keys of the table in sort order.
+    /// Value bytes are never decoded.
+    pub fn key_iter(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<K, redb::Error>> + '_, StorageError> {
+        Ok(self.inner.iter()?.map(|entry| {
+            let (key, _) = entry?;
+            decode_key_with::<C, _>(key.value())
+        }))
     }
-    pub fn as_raw_mut(&mut self) -> &'txn mut redb::Table<'_, sort::SortKey<S>, &'static [u8]> {
-        &mut self.inner
+
+    /// Get all entries of the table, in sort order. The table's length is
+    /// stored, so the result is allocated once with the exact capacity.
+    pub fn to_sorted_vec(&self) -> Result<Vec<(K, V)>, redb::Error> {
+        let len = usize::try_from(self.inner.len()?).unwrap_or(usize::MAX);
+        let mut res = Vec::with_capacity(len);
+        for entry in self.inner.iter()? {
+            res.push(decode_entry::<_, _, _, C>(entry, self.max_value_bytes)?);
+        }
+        Ok(res)
     }
 
-    /// Get a value from the table by key.
-    pub fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V>>, StorageError>
+    /// Get the entries in a key range, in sort order.
+    pub fn get_range<Q, R>(&self, range: R) -> Result<Vec<(K, V)>, redb::Error>
     where
         K: Borrow<Q>,
         Q: bincode::Encode + ?Sized,
+        R: RangeBounds<Q>,
     {
-        unsafe {
-            Ok(with_encode_key_buf(|buf| {
-                let size = bincode::encode_into_std_write(key, buf, BINCODE_CONFIG)
-                    .expect("encoding can't fail");
-                self.inner.get(&buf[..size])
-            })?
-            .map(AccessGuard::from))
+        let range = EncodedRange::new(&range, C::KEY);
+
+        let mut res = vec![];
+        for entry in self.inner.range::<&[u8]>(range.as_bytes())? {
+            res.push(decode_entry::<_, _, _, C>(entry, self.max_value_bytes)?);
         }
+        Ok(res)
     }
 
-    /// Inserts a key and value into the table.
-    /// Returns the previous value, if any.
-    pub fn insert<KQ, VQ>(
-        &mut self,
-        key: &KQ,
-        value: &VQ,
-    ) -> Result<Option<AccessGuard<'_, V>>, StorageError>
-    where
+    /// Get a page of entries in a key range: skip `offset` entries from the
+    /// start of the range, then return up to `limit`. Skipped entries are not
+    /// decoded.
+    pub fn get_range_paged<Q, R>(
+        &self,
+        range: R,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<(K, V)>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let range = EncodedRange::new(&range, C::KEY);
+
+        let mut res = vec![];
+        let mut iter = self.inner.range::<&[u8]>(range.as_bytes())?;
+        skip_entries(&mut iter, offset)?;
+        for entry in iter.take(limit) {
+            let (key, value) = entry?;
+            res.push((
+                decode_key_with::<C, _>(key.value())?,
+                self.decode_value_limited(value.value())?,
+            ));
+        }
+        Ok(res)
+    }
+
+    /// Get every `step`-th entry in a key range, starting with the first,
+    /// such as to downsample a time series. Skipped entries are not decoded.
+    /// A `step` of 0 is taken as 1.
+    pub fn get_range_sampled<Q, R>(&self, range: R, step: usize) -> Result<Vec<(K, V)>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let range = EncodedRange::new(&range, C::KEY);
+
+        let mut res = vec![];
+        let mut iter = self.inner.range::<&[u8]>(range.as_bytes())?;
+        while let Some(entry) = iter.next() {
+            res.push(decode_entry::<_, _, _, C>(entry, self.max_value_bytes)?);
+            skip_entries(&mut iter, step.max(1) - 1)?;
+        }
+        Ok(res)
+    }
+
+    /// Call `f` with each entry in a key range, in sort order, until it
+    /// returns [`ControlFlow::Break`]. Unlike [`ReadOnlyTable::get_range`],
+    /// entries are not collected.
+    pub fn for_each_in_range<Q, R, F>(&self, range: R, mut f: F) -> Result<(), redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+        R: RangeBounds<Q>,
+        F: FnMut(K, V) -> ControlFlow<()>,
+    {
+        let range = EncodedRange::new(&range, C::KEY);
+
+        for entry in self.inner.range::<&[u8]>(range.as_bytes())? {
+            let (key, value) = entry?;
+            let flow = f(
+                decode_key_with::<C, _>(key.value())?,
+                self.decode_value_limited(value.value())?,
+            );
+            if flow.is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// The total size of the encoded keys and values in a key range, such as
+    /// the data of one user. This doesn't count the B-tree overhead, so the
+    /// space taken on disk is larger.
+    pub fn range_bytes<Q, R>(&self, range: R) -> Result<u64, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let range = EncodedRange::new(&range, C::KEY);
+
+        let mut bytes = 0;
+        for entry in self.inner.range::<&[u8]>(range.as_bytes())? {
+            let (key, value) = entry?;
+            bytes += (key.value().len() + value.value().len()) as u64;
+        }
+        Ok(bytes)
+    }
+
+    /// Get the entries of the given keys that are in the table, in table
+    /// order. This is a single pass from the smallest to the largest key,
+    /// decoding only the matching entries.
+    pub fn get_intersection(
+        &self,
+        keys: &std::collections::BTreeSet<K>,
+    ) -> Result<Vec<(K, V)>, redb::Error> {
+        // The set's order needn't match the table's, so sort the encoded keys.
+        let mut wanted: Vec<Vec<u8>> = keys
+            .iter()
+            .map(|key| bincode::encode_to_vec(key, C::KEY).expect("encoding can't fail"))
+            .collect();
+        wanted.sort_by(|a, b| S::compare(a, b));
+        let (Some(first), Some(last)) = (wanted.first(), wanted.last()) else {
+            return Ok(vec![]);
+        };
+
+        let mut res = vec![];
+        let mut wanted_iter = wanted.iter().peekable();
+        for entry in self
+            .inner
+            .range::<&[u8]>(first.as_slice()..=last.as_slice())?
+        {
+            let (key, value) = entry?;
+            while wanted_iter
+                .next_if(|w| S::compare(w, key.value()).is_lt())
+                .is_some()
+            {}
+            let Some(w) = wanted_iter.peek() else {
+                break;
+            };
+            if S::compare(w, key.value()).is_eq() {
+                res.push((
+                    decode_key_with::<C, _>(key.value())?,
+                    self.decode_value_limited(value.value())?,
+                ));
+                wanted_iter.next();
+            }
+        }
+        Ok(res)
+    }
+
+    /// Get the keys of the table that are not in `other`, in sort order, such
+    /// as the keys a remote replica is missing. Values are not decoded.
+    pub fn keys_not_in(
+        &self,
+        other: &std::collections::BTreeSet<K>,
+    ) -> Result<Vec<K>, redb::Error> {
+        // The set's order needn't match the table's, so sort the encoded keys.
+        let mut excluded: Vec<Vec<u8>> = other
+            .iter()
+            .map(|key| bincode::encode_to_vec(key, C::KEY).expect("encoding can't fail"))
+            .collect();
+        excluded.sort_by(|a, b| S::compare(a, b));
+
+        let mut res = vec![];
+        let mut excluded_iter = excluded.iter().peekable();
+        for entry in self.inner.iter()? {
+            let (key, _) = entry?;
+            while excluded_iter
+                .next_if(|e| S::compare(e, key.value()).is_lt())
+                .is_some()
+            {}
+            if excluded_iter
+                .peek()
+                .is_some_and(|e| S::compare(e, key.value()).is_eq())
+            {
+                continue;
+            }
+            res.push(decode_key_with::<C, _>(key.value())?);
+        }
+        Ok(res)
+    }
+
+    /// Count the values by encoded size. `buckets` are ascending upper
+    /// bounds: count `i` is of the values of at most `buckets[i]` bytes that
+    /// don't fit a smaller bucket, and a last count is of those larger than
+    /// every bucket. Values are not decoded.
+    pub fn value_size_histogram(&self, buckets: &[usize]) -> Result<Vec<u64>, redb::Error> {
+        let mut counts = vec![0; buckets.len() + 1];
+        for entry in self.inner.iter()? {
+            let (_, value) = entry?;
+            let len = value.value().len();
+            counts[buckets.partition_point(|&bound| bound < len)] += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Returns whether any key is in a key range. At most one entry is read,
+    /// and nothing is decoded.
+    pub fn any_in_range<Q, R>(&self, range: R) -> Result<bool, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let range = EncodedRange::new(&range, C::KEY);
+        Ok(self
+            .inner
+            .range::<&[u8]>(range.as_bytes())?
+            .next()
+            .transpose()?
+            .is_some())
+    }
+
+    /// Find the entry with the largest encoded value, and return its key
+    /// and the size of its value. Only that key is decoded.
+    pub fn largest_value(&self) -> Result<Option<(K, usize)>, redb::Error> {
+        let mut largest: Option<(Vec<u8>, usize)> = None;
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            let len = value.value().len();
+            if largest.as_ref().is_none_or(|(_, max)| len > *max) {
+                largest = Some((key.value().to_vec(), len));
+            }
+        }
+        largest
+            .map(|(key, len)| Ok((decode_key_with::<C, _>(&key)?, len)))
+            .transpose()
+    }
+
+    /// Count the entries in a key range. Neither keys nor values are
+    /// decoded.
+    pub fn count_range<Q, R>(&self, range: R) -> Result<u64, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let range = EncodedRange::new(&range, C::KEY);
+
+        let mut count = 0;
+        for entry in self.inner.range::<&[u8]>(range.as_bytes())? {
+            entry?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Estimate the number of entries in a key range, such as to choose
+    /// between an index lookup and a scan.
+    ///
+    /// redb keeps no statistics of key ranges to estimate from, so the
+    /// estimate is always exact: the length of the table for an unbounded
+    /// range, which is stored, and otherwise [`ReadOnlyTable::count_range`],
+    /// which scans the range.
+    pub fn estimate_range_len<Q, R>(&self, range: R) -> Result<u64, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        if let (Bound::Unbounded, Bound::Unbounded) = (range.start_bound(), range.end_bound()) {
+            return Ok(self.inner.len()?);
+        }
+        self.count_range(range)
+    }
+
+    /// Count the keys that start with `prefix`, such as the leading field of
+    /// a tuple key. Values are not decoded.
+    pub fn count_prefix<P>(&self, prefix: &P) -> Result<u64, redb::Error>
+    where
+        P: bincode::Encode + ?Sized,
+    {
+        let prefix = bincode::encode_to_vec(prefix, C::KEY).expect("encoding can't fail");
+
+        let mut count = 0;
+        for entry in self.inner.range::<&[u8]>(prefix.as_slice()..)? {
+            let (key, _) = entry?;
+            if !key.value().starts_with(&prefix) {
+                break;
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Get the entries of keys `(prefix, t)` with `t` in `range`, such as
+    /// the events of one user in a time range, for keys of `(user, time)`.
+    ///
+    /// The range is over encoded bytes, so it needs `T` to encode in
+    /// order, as unsigned integers do (see [`Encoding`]).
+    pub fn get_sub_range<P, T, R>(&self, prefix: &P, range: R) -> Result<Entries<K, V>, redb::Error>
+    where
+        P: bincode::Encode + ?Sized,
+        T: bincode::Encode + ?Sized,
+        R: RangeBounds<T>,
+    {
+        let prefix = bincode::encode_to_vec(prefix, C::KEY).expect("encoding can't fail");
+        let with_prefix = |t: &T| {
+            let mut key = prefix.clone();
+            bincode::encode_into_std_write(t, &mut key, C::KEY).expect("encoding can't fail");
+            key
+        };
+        let start = match range.start_bound() {
+            Bound::Unbounded => Bound::Included(prefix.clone()),
+            bound => bound.map(with_prefix),
+        };
+        let end = range.end_bound().map(with_prefix);
+
+        let mut res = vec![];
+        for entry in self.inner.range::<&[u8]>((
+            start.as_ref().map(Vec::as_slice),
+            end.as_ref().map(Vec::as_slice),
+        ))? {
+            let (key, value) = entry?;
+            if !key.value().starts_with(&prefix) {
+                break;
+            }
+            res.push((
+                decode_key_with::<C, _>(key.value())?,
+                self.decode_value_limited(value.value())?,
+            ));
+        }
+        Ok(res)
+    }
+
+    /// Iterate over the table in groups of consecutive entries that share a
+    /// group key, as extracted from each key by `f`.
+    ///
+    /// Since entries are sorted, a key prefix such as the leading field of a
+    /// tuple keeps its rows contiguous, so grouping takes a single pass.
+    pub fn group_by_prefix<'a, P, F>(
+        &'a self,
+        mut f: F,
+    ) -> Result<impl Iterator<Item = Result<Group<P, K, V>, redb::Error>> + 'a, StorageError>
+    where
+        P: PartialEq + 'a,
+        F: FnMut(&K) -> P + 'a,
+    {
+        let mut iter = self.inner.iter()?;
+        let mut pending: Option<(P, K, V)> = None;
+
+        Ok(std::iter::from_fn(move || {
+            let mut group = pending.take().map(|(p, k, v)| (p, vec![(k, v)]));
+            loop {
+                let Some(entry) = iter.next() else {
+                    return group.map(Ok);
+                };
+                let (key, value) = match decode_entry::<_, _, _, C>(entry, self.max_value_bytes) {
+                    Ok(entry) => entry,
+                    Err(e) => return Some(Err(e)),
+                };
+
+                let prefix = f(&key);
+                match &mut group {
+                    Some((p, rows)) if *p == prefix => rows.push((key, value)),
+                    Some(_) => {
+                        pending = Some((prefix, key, value));
+                        return group.map(Ok);
+                    }
+                    None => group = Some((prefix, vec![(key, value)])),
+                }
+            }
+        }))
+    }
+
+    /// Find the first key, in sort order, whose value is `value`.
+    ///
+    /// This scans the table, so it takes time linear in its size; keep a
+    /// secondary index, see [`IndexedTable`], for frequent lookups. Values
+    /// are compared by their encoded bytes, so none is decoded.
+    pub fn find_key_by_value(&self, value: &V) -> Result<Option<K>, redb::Error> {
+        let target = bincode::encode_to_vec(value, C::VALUE).expect("encoding can't fail");
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            if value.value() == target.as_slice() {
+                return Ok(Some(decode_key_with::<C, _>(key.value())?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find the values stored under more than one key, comparing their
+    /// encoded bytes exactly. Returns the encoded value of each such group
+    /// with its keys, ordered by their first key. Values are not decoded.
+    pub fn find_duplicate_values(&self) -> Result<DuplicateValues<K>, redb::Error> {
+        let mut groups: DuplicateValues<K> = vec![];
+        // Groups by the hash of their value, so that each value is only kept
+        // once, in `groups`.
+        let mut index: std::collections::HashMap<u64, Vec<usize>> =
+            std::collections::HashMap::new();
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            let key = decode_key_with::<C, _>(key.value())?;
+            let value = value.value();
+            let candidates = index.entry(fnv1a(FNV_OFFSET, value)).or_default();
+            match candidates.iter().find(|&&i| groups[i].0 == value) {
+                Some(&i) => groups[i].1.push(key),
+                None => {
+                    candidates.push(groups.len());
+                    groups.push((value.to_vec(), vec![key]));
+                }
+            }
+        }
+        groups.retain(|(_, keys)| keys.len() > 1);
+        Ok(groups)
+    }
+
+    /// Iterate over the entries of the table in sort order, reporting each
+    /// entry that fails to decode with its encoded key, and carrying on.
+    ///
+    /// Storage errors are reported by the outer result of each item.
+    /// Unlike [`ReadOnlyTable::validate`], entries are decoded lazily and
+    /// returned.
+    #[allow(clippy::type_complexity)]
+    pub fn iter_checked(
+        &self,
+    ) -> Result<
+        impl Iterator<
+                Item = Result<Result<(K, V), (Vec<u8>, bincode::error::DecodeError)>, StorageError>,
+            > + '_,
+        StorageError,
+    > {
+        Ok(self.inner.iter()?.map(|entry| {
+            let (key, value) = entry?;
+            let decoded = (|| -> Result<(K, V), bincode::error::DecodeError> {
+                let (k, _) = bincode::decode_from_slice(key.value(), C::KEY)?;
+                let v = decode_value_checked::<C, _>(value.value(), self.max_value_bytes)?;
+                Ok((k, v))
+            })();
+            Ok(decoded.map_err(|e| (key.value().to_vec(), e)))
+        }))
+    }
+
+    /// Decode and process every entry of the table on the rayon thread pool,
+    /// in no particular order. Stops at the first entry that fails to decode.
+    ///
+    /// Only decoding and `f` run in parallel: the table is first read in a
+    /// single thread, and its encoded entries are all held in memory.
+    #[cfg(feature = "rayon")]
+    pub fn par_for_each<F>(&self, f: F) -> Result<(), redb::Error>
+    where
+        F: Fn(K, V) + Sync,
+    {
+        use rayon::prelude::*;
+
+        let raw = self.iter_raw()?.collect::<Result<Vec<_>, _>>()?;
+        let limit = self.max_value_bytes;
+        raw.par_iter().try_for_each(|(key, value)| {
+            let value = decode_value_checked::<C, _>(value, limit).map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+            f(decode_key_with::<C, _>(key)?, value);
+            Ok(())
+        })
+    }
+
+    /// Iterate over the raw encoded key and value bytes of the table, in sort
+    /// order. Useful to inspect how keys are actually ordered.
+    pub fn iter_raw(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<RawEntry, redb::Error>> + '_, redb::Error> {
+        Ok(self.inner.iter()?.map(|entry| {
+            let (key, value) = entry?;
+            Ok((key.value().to_vec(), value.value().to_vec()))
+        }))
+    }
+
+    /// Try to decode every row of the table. Unlike iteration, this doesn't
+    /// stop at the first row that fails to decode, but reports all of them.
+    pub fn validate(&self) -> Result<ValidationReport, redb::Error> {
+        let mut report = ValidationReport::default();
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            let decoded = bincode::decode_from_slice::<K, _>(key.value(), C::KEY).is_ok()
+                && self.decode_value_limited::<V>(value.value()).is_ok();
+            if decoded {
+                report.ok += 1;
+            } else {
+                report.failed.push(key.value().to_vec());
+            }
+        }
+        Ok(report)
+    }
+
+    /// A hash of all encoded keys and values, in sort order.
+    /// Tables with identical contents have identical checksums, so this can
+    /// tell whether two replicas are in sync. The hash is stable across
+    /// releases and platforms.
+    pub fn checksum(&self) -> Result<u64, redb::Error> {
+        let mut hash = FNV_OFFSET;
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            // Hash the lengths too, so moving bytes between key and value
+            // changes the checksum.
+            for bytes in [key.value(), value.value()] {
+                hash = fnv1a(hash, &(bytes.len() as u64).to_be_bytes());
+                hash = fnv1a(hash, bytes);
+            }
+        }
+        Ok(hash)
+    }
+
+    /// Write every entry of the table to `w`, in sort order, as
+    /// `len(key) || key || len(value) || value` with big-endian `u64`
+    /// lengths and the stored bytes. Read it back with
+    /// [`Table::import_from`]. Returns the number of written entries.
+    pub fn export_to<W: std::io::Write>(&self, w: &mut W) -> Result<u64, redb::Error> {
+        let mut count = 0;
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            for bytes in [key.value(), value.value()] {
+                w.write_all(&(bytes.len() as u64).to_be_bytes())
+                    .and_then(|()| w.write_all(bytes))
+                    .map_err(redb::Error::Io)?;
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Get the first entry, in sort order, matching the given predicate.
+    pub fn find_first<F>(&self, f: F) -> Result<Option<(K, V)>, redb::Error>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.find(self.inner.iter()?, f)
+    }
+
+    /// Get the last entry, in sort order, matching the given predicate.
+    /// The table is scanned from the end.
+    pub fn find_last<F>(&self, f: F) -> Result<Option<(K, V)>, redb::Error>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.find(self.inner.iter()?.rev(), f)
+    }
+
+    /// Returns the first of `entries` matching `f`.
+    #[allow(clippy::type_complexity)]
+    fn find<'a, F>(
+        &self,
+        entries: impl Iterator<
+            Item = Result<
+                (
+                    redb::AccessGuard<'a, sort::SortKey<S>>,
+                    redb::AccessGuard<'a, &'static [u8]>,
+                ),
+                StorageError,
+            >,
+        >,
+        mut f: F,
+    ) -> Result<Option<(K, V)>, redb::Error>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        for entry in entries {
+            let (key, value) = decode_entry::<_, _, _, C>(entry, self.max_value_bytes)?;
+            if f(&key, &value) {
+                return Ok(Some((key, value)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get the entry with the largest key at or before `key`.
+    ///
+    /// Keys are compared by their encoded bytes, so this only matches the
+    /// order of `K` if it encodes in order, as unsigned integers do.
+    pub fn floor<Q>(&self, key: &Q) -> Result<Option<(K, V)>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        let entry = unsafe {
+            with_encode_key_buf(|buf| {
+                let size =
+                    bincode::encode_into_std_write(key, buf, C::KEY).expect("encoding can't fail");
+                self.inner
+                    .range::<&[u8]>(..=&buf[..size])?
+                    .next_back()
+                    .transpose()
+            })
+        }?;
+        self.decode_found(entry)
+    }
+
+    /// Get the entry with the smallest key at or after `key`. See
+    /// [`ReadOnlyTable::floor`] about the order of keys.
+    pub fn ceil<Q>(&self, key: &Q) -> Result<Option<(K, V)>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        let entry = unsafe {
+            with_encode_key_buf(|buf| {
+                let size =
+                    bincode::encode_into_std_write(key, buf, C::KEY).expect("encoding can't fail");
+                self.inner
+                    .range::<&[u8]>(&buf[..size]..)?
+                    .next()
+                    .transpose()
+            })
+        }?;
+        self.decode_found(entry)
+    }
+
+    /// Decodes an entry found by a lookup, if any.
+    #[allow(clippy::type_complexity)]
+    fn decode_found(
+        &self,
+        entry: Option<(
+            redb::AccessGuard<'_, sort::SortKey<S>>,
+            redb::AccessGuard<'_, &'static [u8]>,
+        )>,
+    ) -> Result<Option<(K, V)>, redb::Error> {
+        let Some((key, value)) = entry else {
+            return Ok(None);
+        };
+        Ok(Some((
+            decode_key_with::<C, _>(key.value())?,
+            self.decode_value_limited(value.value())?,
+        )))
+    }
+
+    /// Get the entry at sorted position `n`.
+    /// This walks the table from the start, so it costs O(n).
+    pub fn select(&self, n: usize) -> Result<Option<(K, V)>, redb::Error> {
+        let mut iter = self.inner.iter()?;
+        skip_entries(&mut iter, n)?;
+        let Some(entry) = iter.next() else {
+            return Ok(None);
+        };
+        let (key, value) = entry?;
+        Ok(Some((
+            decode_key_with::<C, _>(key.value())?,
+            self.decode_value_limited(value.value())?,
+        )))
+    }
+
+    /// Get the sorted position of a key, the inverse of
+    /// [`ReadOnlyTable::select`]. Returns `None` if the key is absent.
+    /// This counts the preceding keys, so it costs O(rank).
+    pub fn rank<Q>(&self, key: &Q) -> Result<Option<usize>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        unsafe {
+            with_encode_key_buf(|buf| {
+                let size =
+                    bincode::encode_into_std_write(key, buf, C::KEY).expect("encoding can't fail");
+                let key = &buf[..size];
+                if self.inner.get(key)?.is_none() {
+                    return Ok(None);
+                }
+
+                let mut rank = 0;
+                for entry in self.inner.range::<&[u8]>(..key)? {
+                    entry?;
+                    rank += 1;
+                }
+                Ok(Some(rank))
+            })
+        }
+    }
+
+    /// Wraps a stored value, carrying over the table's size limit.
+    fn guard(
+        &self,
+        inner: redb::AccessGuard<'static, &'static [u8]>,
+    ) -> AccessGuard<'static, V, C> {
+        AccessGuard {
+            inner,
+            limit: self.max_value_bytes,
+            _v: PhantomData,
+            _c: PhantomData,
+        }
+    }
+
+    /// Decodes a value, unless it is over the table's size limit.
+    fn decode_value_limited<T: bincode::Decode>(&self, data: &[u8]) -> Result<T, redb::Error> {
+        decode_value_checked::<C, _>(data, self.max_value_bytes)
+            .map_err(|e| redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+    }
+
+    /// Get metadata about the table.
+    pub fn stats(&self) -> Result<redb::TableStats, redb::StorageError> {
+        self.inner.stats()
+    }
+}
+
+/// A mutable table in the database.
+pub struct Table<'txn, K, V, S, C = Bincode>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    inner: redb::Table<'txn, sort::SortKey<S>, &'static [u8]>,
+    changes: watch::TableChanges<'txn>,
+    max_value_bytes: Option<usize>,
+    _k: PhantomData<K>,
+    _v: PhantomData<V>,
+    _c: PhantomData<C>,
+}
+
+
+
+
+impl<'txn, K, V, S, C> Table<'txn, K, V, S, C>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+    C: Encoding,
+{
+    pub fn as_raw(&self) -> &redb::Table<sort::SortKey<S>, &'static [u8]> {
+        &self.inner
+    }
+    pub fn as_raw_mut(&mut self) -> &'txn mut redb::Table<'_, sort::SortKey<S>, &'static [u8]> {
+        &mut self.inner
+    }
+
+    /// Get a value from the table by key.
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<AccessGuard<'_, V, C>>, StorageError>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        unsafe {
+            Ok(with_encode_key_buf(|buf| {
+                let size =
+                    bincode::encode_into_std_write(key, buf, C::KEY).expect("encoding can't fail");
+                self.inner.get(&buf[..size])
+            })?
+            .map(AccessGuard::from))
+        }
+    }
+
+    /// Encode a key once, for use with [`Table::get_raw`] and
+    /// [`Table::insert_raw`].
+    pub fn encode_key<Q>(&self, key: &Q) -> RawKey<K, C>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        RawKey::encode(key)
+    }
+
+    /// Returns whether the table contains the key. The value is not decoded.
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool, StorageError>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Get a value from the table by an already encoded key.
+    pub fn get_raw(
+        &self,
+        key: &RawKey<K, C>,
+    ) -> Result<Option<AccessGuard<'_, V, C>>, StorageError> {
+        Ok(self.inner.get(key.as_bytes())?.map(AccessGuard::from))
+    }
+
+    /// Inserts a value under an already encoded key.
+    /// Returns the previous value, if any.
+    pub fn insert_raw<VQ>(
+        &mut self,
+        key: &RawKey<K, C>,
+        value: &VQ,
+    ) -> Result<Option<AccessGuard<'_, V, C>>, StorageError>
+    where
+        V: Borrow<VQ>,
+        VQ: bincode::Encode + ?Sized,
+    {
+        unsafe {
+            with_encode_value_buf(|value_buf| {
+                let value_size = bincode::encode_into_std_write(value, value_buf, C::VALUE)
+                    .expect("encoding can't fail");
+
+                self.insert_bytes(key.as_bytes(), &value_buf[..value_size])
+            })
+        }
+    }
+
+    /// Replace the value of a key with `f` applied to its current value, if
+    /// any. The key is encoded once for both the lookup and the insert.
+    /// Returns the new value.
+    pub fn update<KQ, F>(&mut self, key: &KQ, f: F) -> Result<V, redb::Error>
+    where
+        K: Borrow<KQ>,
+        KQ: bincode::Encode + ?Sized,
+        F: FnOnce(Option<V>) -> V,
+    {
+        let key = self.encode_key(key);
+        let prev = self
+            .get_raw(&key)?
+            .map(|v| v.value())
+            .transpose()
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+
+        let value = f(prev);
+        self.insert_raw(&key, &value)?;
+        Ok(value)
+    }
+
+    /// Apply `update` to the value of every entry accepted by `predicate`,
+    /// and store the result. Returns the number of updated entries.
+    pub fn update_where<P, U>(
+        &mut self,
+        mut predicate: P,
+        mut update: U,
+    ) -> Result<u64, redb::Error>
+    where
+        P: FnMut(&K, &V) -> bool,
+        U: FnMut(&mut V),
+    {
+        let mut updates = vec![];
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            let k: K = decode_key_with::<C, _>(key.value())?;
+            let mut v: V = decode_value_with::<C, _>(value.value())?;
+            if predicate(&k, &v) {
+                update(&mut v);
+                let v = bincode::encode_to_vec(&v, C::VALUE).expect("encoding can't fail");
+                updates.push((key.value().to_vec(), v));
+            }
+        }
+
+        for (key, value) in &updates {
+            self.insert_bytes(key, value)?;
+        }
+        Ok(updates.len() as u64)
+    }
+
+    /// Inserts a key and value into the table, unless the key is already
+    /// present. The key is encoded once for both the check and the insert.
+    /// Returns whether the value was inserted.
+    pub fn insert_if_absent<KQ, VQ>(&mut self, key: &KQ, value: &VQ) -> Result<bool, redb::Error>
+    where
         K: Borrow<KQ>,
         V: Borrow<VQ>,
         KQ: bincode::Encode + ?Sized,
         VQ: bincode::Encode + ?Sized,
     {
-        Ok(unsafe {
+        let key = self.encode_key(key);
+        if self.get_raw(&key)?.is_some() {
+            return Ok(false);
+        }
+        self.insert_raw(&key, value)?;
+        Ok(true)
+    }
+
+    /// Add `delta` to the integer value of a key, which counts as 0 if
+    /// absent. The key is encoded once for both the lookup and the insert.
+    /// Returns the new value, or an error if it doesn't fit in `V`, in which
+    /// case nothing is stored.
+    pub fn increment<KQ>(&mut self, key: &KQ, delta: i64) -> Result<i64, redb::Error>
+    where
+        K: Borrow<KQ>,
+        KQ: bincode::Encode + ?Sized,
+        V: TryFrom<i64> + TryInto<i64>,
+    {
+        let overflow = || {
+            redb::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "increment overflows the value type",
+            ))
+        };
+
+        let key = self.encode_key(key);
+        let prev: i64 = match self.get_raw(&key)? {
+            Some(prev) => decode_value_with::<C, V>(prev.inner.value())?
+                .try_into()
+                .map_err(|_| overflow())?,
+            None => 0,
+        };
+
+        let value = prev.checked_add(delta).ok_or_else(overflow)?;
+        let encoded = V::try_from(value).map_err(|_| overflow())?;
+        self.insert_raw(&key, &encoded)?;
+        Ok(value)
+    }
+
+    /// Inserts a key and value into the table.
+    /// Returns the previous value, if any.
+    pub fn insert<KQ, VQ>(
+        &mut self,
+        key: &KQ,
+        value: &VQ,
+    ) -> Result<Option<AccessGuard<'_, V, C>>, StorageError>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KQ: bincode::Encode + ?Sized,
+        VQ: bincode::Encode + ?Sized,
+    {
+        unsafe {
             with_encode_key_buf(|key_buf| {
-                let key_size = bincode::encode_into_std_write(key, key_buf, BINCODE_CONFIG)
+                let key_size = bincode::encode_into_std_write(key, key_buf, C::KEY)
                     .expect("encoding can't fail");
 
                 with_encode_value_buf(|value_buf| {
-                    let value_size =
-                        bincode::encode_into_std_write(value, value_buf, BINCODE_CONFIG)
-                            .expect("encoding can't fail");
+                    let value_size = bincode::encode_into_std_write(value, value_buf, C::VALUE)
+                        .expect("encoding can't fail");
 
-                    self.inner
-                        .insert(&key_buf[..key_size], &value_buf[..value_size])
+                    self.insert_bytes(&key_buf[..key_size], &value_buf[..value_size])
                 })
             })
-        }?
-        .map(AccessGuard::from))
+        }
+    }
+
+    /// Inserts a key and value into the table.
+    /// Returns the decoded previous value, if any.
+    pub fn replace<KQ, VQ>(&mut self, key: &KQ, value: &VQ) -> Result<Option<V>, redb::Error>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KQ: bincode::Encode + ?Sized,
+        VQ: bincode::Encode + ?Sized,
+    {
+        let Some(prev) = self.insert(key, value)? else {
+            return Ok(None);
+        };
+        Ok(Some(decode_value_with::<C, _>(prev.inner.value())?))
     }
 
     /// Remove a value from the table by key.
     /// Returns the value that was removed, if any.
-    pub fn remove<KQ>(&mut self, key: &KQ) -> Result<Option<AccessGuard<'_, V>>, redb::Error>
+    pub fn remove<KQ>(&mut self, key: &KQ) -> Result<Option<AccessGuard<'_, V, C>>, redb::Error>
     where
         K: Borrow<KQ>,
         KQ: bincode::Encode + ?Sized,
     {
         Ok(unsafe {
             with_encode_key_buf(|key_buf| {
-                let key_size = bincode::encode_into_std_write(key, key_buf, BINCODE_CONFIG)
+                let key_size = bincode::encode_into_std_write(key, key_buf, C::KEY)
                     .expect("encoding can't fail");
-                self.inner.remove(&key_buf[..key_size])
+                self.remove_bytes(&key_buf[..key_size])
             })
-        }?
-        .map(AccessGuard::from))
+        }?)
+    }
+
+    /// Remove the given keys from the table.
+    /// Returns the removed values, in the order of `keys`.
+    pub fn remove_many<Q>(&mut self, keys: &[Q]) -> Result<Vec<Option<V>>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode,
+    {
+        unsafe {
+            with_encode_key_buf(|key_buf| {
+                let mut res = Vec::with_capacity(keys.len());
+                for key in keys {
+                    key_buf.clear();
+                    let key_size = bincode::encode_into_std_write(key, key_buf, C::KEY)
+                        .expect("encoding can't fail");
+                    let removed = match self.remove_bytes(&key_buf[..key_size])? {
+                        Some(value) => Some(decode_value_with::<C, V>(value.inner.value())?),
+                        None => None,
+                    };
+                    res.push(removed);
+                }
+                Ok(res)
+            })
+        }
+    }
+
+    /// Move entries to new keys: for each key for which `f` returns a new
+    /// key, the value is moved under it, unchanged.
+    /// Returns the number of moved entries.
+    ///
+    /// Fails without changing anything if a new key is already used by an
+    /// entry that isn't moved, or if two entries are moved to the same key.
+    pub fn remap_keys<F>(&mut self, mut f: F) -> Result<u64, redb::Error>
+    where
+        F: FnMut(&K) -> Option<K>,
+    {
+        let mut moves = vec![];
+        for entry in self.inner.iter()? {
+            let (key, value) = entry?;
+            if let Some(new_key) = f(&decode_key_with::<C, K>(key.value())?) {
+                let new_key =
+                    bincode::encode_to_vec(&new_key, C::KEY).expect("encoding can't fail");
+                moves.push((key.value().to_vec(), new_key, value.value().to_vec()));
+            }
+        }
+
+        let old_keys: std::collections::HashSet<&[u8]> =
+            moves.iter().map(|(old, _, _)| old.as_slice()).collect();
+        let mut new_keys: std::collections::HashSet<&[u8]> = std::collections::HashSet::new();
+        for (_, new, _) in &moves {
+            let collides = !new_keys.insert(new.as_slice())
+                || (!old_keys.contains(new.as_slice())
+                    && self.inner.get(new.as_slice())?.is_some());
+            if collides {
+                return Err(redb::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "remapped key collides with another key",
+                )));
+            }
+        }
+
+        for (old, _, _) in &moves {
+            self.remove_bytes(old)?;
+        }
+        for (_, new, value) in &moves {
+            self.insert_bytes(new, value)?;
+        }
+        Ok(moves.len() as u64)
+    }
+
+    /// Inserts already encoded bytes, recording the change for subscribers.
+    /// Every insert goes through here, so this is where the maximum value
+    /// size is enforced.
+    fn insert_bytes(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<AccessGuard<'_, V, C>>, StorageError> {
+        if self.max_value_bytes.is_some_and(|max| value.len() > max) {
+            return Err(StorageError::ValueTooLarge(value.len()));
+        }
+        let prev = self.inner.insert(key, value)?;
+        self.changes.record(key, ChangeKind::Insert);
+        Ok(prev.map(AccessGuard::from))
+    }
+
+    /// Removes by an already encoded key, recording the change for subscribers.
+    fn remove_bytes(&mut self, key: &[u8]) -> Result<Option<AccessGuard<'_, V, C>>, StorageError> {
+        let removed = self.inner.remove(key)?;
+        if removed.is_some() {
+            self.changes.record(key, ChangeKind::Remove);
+        }
+        Ok(removed.map(AccessGuard::from))
+    }
+
+    /// Insert every entry written by [`ReadOnlyTable::export_to`] from `r`,
+    /// replacing existing values of the same keys. The bytes are stored as
+    /// is, without decoding. Returns the number of imported entries.
+    pub fn import_from<R: std::io::Read>(&mut self, r: &mut R) -> Result<u64, redb::Error> {
+        let mut count = 0;
+        while let Some(key) = read_field(r).map_err(redb::Error::Io)? {
+            let value = read_field(r)
+                .and_then(|v| v.ok_or(std::io::ErrorKind::UnexpectedEof.into()))
+                .map_err(redb::Error::Io)?;
+            self.insert_bytes(&key, &value)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Remove the entries of the table as they are yielded, in sort order.
+    /// Dropping the iterator early leaves the remaining entries in place.
+    pub fn drain(
+        &mut self,
+    ) -> Result<impl Iterator<Item = Result<(K, V), redb::Error>> + '_, redb::Error> {
+        let changes = &self.changes;
+        Ok(self.inner.extract_if(|_, _| true)?.map(move |entry| {
+            let (key, value) = entry?;
+            changes.record(key.value(), ChangeKind::Remove);
+            Ok((
+                decode_key_with::<C, _>(key.value())?,
+                decode_value_with::<C, _>(value.value())?,
+            ))
+        }))
     }
 
     /// Remove a range of values from the table with a given predicate.
@@ -320,18 +1954,18 @@ where
         let res = self
             .inner
             .extract_if(|key, value| {
-                let (key, _): (K, usize) = bincode::decode_from_slice(key, BINCODE_CONFIG).unwrap();
-                let (value, _): (V, usize) =
-                    bincode::decode_from_slice(value, BINCODE_CONFIG).unwrap();
+                let (key, _): (K, usize) = bincode::decode_from_slice(key, C::KEY).unwrap();
+                let (value, _): (V, usize) = bincode::decode_from_slice(value, C::VALUE).unwrap();
                 predicate((key, value))
             })?
             .into_iter()
             .map(|d| {
                 let (k, v) = d.unwrap();
+                self.changes.record(k.value(), ChangeKind::Remove);
                 let key: Result<(K, usize), bincode::error::DecodeError> =
-                    bincode::decode_from_slice(k.value(), BINCODE_CONFIG);
+                    bincode::decode_from_slice(k.value(), C::KEY);
                 let value: Result<(V, usize), bincode::error::DecodeError> =
-                    bincode::decode_from_slice(v.value(), BINCODE_CONFIG);
+                    bincode::decode_from_slice(v.value(), C::VALUE);
 
                 if let Ok((k, _)) = key {
                     if let Ok((v, _)) = value {
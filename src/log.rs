@@ -0,0 +1,170 @@
+use std::ops::Bound;
+
+use redb::ReadableTable;
+
+use super::{
+    decode_key_with, decode_value, skip_entries, ChangeKind, Encoding, Lexicographical, Ordered,
+    Table,
+};
+
+/// An append-only log of values, keyed by their sequence number.
+///
+/// Sequence numbers are encoded as big-endian fixed-width integers, see
+/// [`Ordered`], so the log iterates in append order. The next sequence number
+/// is kept in a `<name>.seq` sidecar table, so numbers are never reused, even
+/// once the log is trimmed or emptied.
+pub struct OrderedLogTable<'txn, V> {
+    inner: Table<'txn, u64, V, Lexicographical, Ordered>,
+    next: Table<'txn, (), u64, Lexicographical>,
+}
+
+impl<'txn, V> OrderedLogTable<'txn, V>
+where
+    V: bincode::Encode + bincode::Decode,
+{
+    pub(crate) fn new(
+        inner: Table<'txn, u64, V, Lexicographical, Ordered>,
+        next: Table<'txn, (), u64, Lexicographical>,
+    ) -> Self {
+        Self { inner, next }
+    }
+
+    /// Returns the underlying table of sequence numbers and values.
+    pub fn as_table(&self) -> &Table<'txn, u64, V, Lexicographical, Ordered> {
+        &self.inner
+    }
+
+    /// Append a value to the log and return its sequence number.
+    pub fn append(&mut self, value: &V) -> Result<u64, redb::Error> {
+        let next = self
+            .next
+            .get(&())?
+            .map(|v| v.value())
+            .transpose()
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+        let seq = match next {
+            Some(seq) => seq,
+            // Nothing was appended through the log yet, so continue after
+            // any entries inserted into the underlying table.
+            None => match self.inner.inner.last()? {
+                Some((key, _)) => decode_key_with::<Ordered, u64>(key.value())? + 1,
+                None => 0,
+            },
+        };
+        let next = seq
+            .checked_add(1)
+            .ok_or_else(|| redb::Error::Io(std::io::Error::other("log sequence overflowed")))?;
+        self.inner.insert(&seq, value)?;
+        self.next.insert(&(), &next)?;
+        Ok(seq)
+    }
+
+    /// Iterate over the entries of the log in append order.
+    pub fn iter(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<(u64, V), redb::Error>> + '_, redb::Error> {
+        Ok(self.inner.inner.iter()?.map(|entry| {
+            let (key, value) = entry?;
+            Ok((
+                decode_key_with::<Ordered, _>(key.value())?,
+                decode_value(value.value())?,
+            ))
+        }))
+    }
+
+    /// Get the last `n` entries of the log, in append order. Only those
+    /// entries are read.
+    pub fn tail(&self, n: usize) -> Result<Vec<(u64, V)>, redb::Error> {
+        let mut res = Vec::with_capacity(n);
+        for entry in self.inner.inner.iter()?.rev().take(n) {
+            let (key, value) = entry?;
+            res.push((
+                decode_key_with::<Ordered, _>(key.value())?,
+                decode_value(value.value())?,
+            ));
+        }
+        res.reverse();
+        Ok(res)
+    }
+
+    /// Remove all but the last `n` entries of the log, and return the number
+    /// of removed entries.
+    pub fn trim_to_last(&mut self, n: u64) -> Result<u64, redb::Error> {
+        // Everything before the oldest entry to keep is removed.
+        let end = match n.checked_sub(1) {
+            Some(skip) => {
+                let skip = usize::try_from(skip).unwrap_or(usize::MAX);
+                let mut iter = self.inner.inner.iter()?.rev();
+                skip_entries(&mut iter, skip)?;
+                match iter.next() {
+                    Some(entry) => Bound::Excluded(entry?.0.value().to_vec()),
+                    None => return Ok(0),
+                }
+            }
+            None => Bound::Unbounded,
+        };
+
+        self.remove_before(end.as_ref().map(Vec::as_slice))
+    }
+
+    /// Remove the entries whose encoded key is before `end`, and return how
+    /// many were removed.
+    fn remove_before(&mut self, end: Bound<&[u8]>) -> Result<u64, redb::Error> {
+        let mut removed = 0;
+        for entry in self
+            .inner
+            .inner
+            .extract_from_if::<&[u8], _>((Bound::Unbounded, end), |_, _| true)?
+        {
+            let (key, _) = entry?;
+            self.inner.changes.record(key.value(), ChangeKind::Remove);
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+/// A log that keeps only its last `capacity` entries, such as a bounded
+/// buffer of recent events.
+///
+/// Each push removes the entries that fell out of the buffer, in the write
+/// transaction of the push.
+pub struct RingTable<'txn, V> {
+    log: OrderedLogTable<'txn, V>,
+    capacity: u64,
+}
+
+impl<'txn, V> RingTable<'txn, V>
+where
+    V: bincode::Encode + bincode::Decode,
+{
+    pub(crate) fn new(log: OrderedLogTable<'txn, V>, capacity: u64) -> Self {
+        Self { log, capacity }
+    }
+
+    /// Returns the underlying log.
+    pub fn as_log(&self) -> &OrderedLogTable<'txn, V> {
+        &self.log
+    }
+
+    /// Append a value, removing the oldest entries beyond the capacity.
+    /// Returns the sequence number of the value.
+    pub fn push(&mut self, value: &V) -> Result<u64, redb::Error> {
+        let seq = self.log.append(value)?;
+        // Sequence numbers are contiguous, so the oldest kept one is known.
+        if let Some(oldest) = (seq + 1).checked_sub(self.capacity) {
+            let oldest = bincode::encode_to_vec(oldest, Ordered::KEY).expect("encoding can't fail");
+            self.log.remove_before(Bound::Excluded(oldest.as_slice()))?;
+        }
+        Ok(seq)
+    }
+
+    /// Iterate over the entries, from the oldest to the newest.
+    pub fn iter(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<(u64, V), redb::Error>> + '_, redb::Error> {
+        self.log.iter()
+    }
+}
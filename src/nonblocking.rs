@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use crate::Database;
+
+/// A [`Database`] for use from async code.
+///
+/// Every operation runs on tokio's blocking thread pool via
+/// [`tokio::task::spawn_blocking`], so it doesn't stall the async runtime.
+/// A long-running operation, such as a large transaction in [`run`], still
+/// occupies a blocking thread until it finishes.
+///
+/// [`run`]: AsyncDatabase::run
+#[derive(Clone)]
+pub struct AsyncDatabase {
+    inner: Arc<Database>,
+}
+
+impl From<Database> for AsyncDatabase {
+    fn from(value: Database) -> Self {
+        Self {
+            inner: Arc::new(value),
+        }
+    }
+}
+
+impl From<Arc<Database>> for AsyncDatabase {
+    fn from(inner: Arc<Database>) -> Self {
+        Self { inner }
+    }
+}
+
+impl AsyncDatabase {
+    /// Returns the underlying database, for blocking use.
+    pub fn as_sync(&self) -> &Arc<Database> {
+        &self.inner
+    }
+
+    /// Run `f` against the database on the blocking thread pool.
+    pub async fn run<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&Database) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let db = self.inner.clone();
+        match tokio::task::spawn_blocking(move || f(&db)).await {
+            Ok(res) => res,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    /// Get a value from a table by key, in its own read transaction.
+    pub async fn get<K, V>(&self, table: &str, key: K) -> Result<Option<V>, redb::Error>
+    where
+        K: bincode::Encode + bincode::Decode + Send + 'static,
+        V: bincode::Encode + bincode::Decode + Send + 'static,
+    {
+        let table = table.to_owned();
+        self.run(move |db| {
+            let txn = db.begin_read()?;
+            let table = txn.open_table::<K, V>(&table)?;
+            table
+                .get(&key)?
+                .map(|v| v.value())
+                .transpose()
+                .map_err(|e| {
+                    redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+                })
+        })
+        .await
+    }
+
+    /// Insert a key and value into a table, in its own write transaction.
+    pub async fn insert<K, V>(&self, table: &str, key: K, value: V) -> Result<(), redb::Error>
+    where
+        K: bincode::Encode + bincode::Decode + Send + 'static,
+        V: bincode::Encode + bincode::Decode + Send + 'static,
+    {
+        let table = table.to_owned();
+        self.run(move |db| {
+            let txn = db.begin_write()?;
+            txn.open_table::<K, V>(&table)?.insert(&key, &value)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Remove a key from a table, in its own write transaction.
+    /// Returns the value that was removed, if any.
+    pub async fn remove<K, V>(&self, table: &str, key: K) -> Result<Option<V>, redb::Error>
+    where
+        K: bincode::Encode + bincode::Decode + Send + 'static,
+        V: bincode::Encode + bincode::Decode + Send + 'static,
+    {
+        let table = table.to_owned();
+        self.run(move |db| {
+            let txn = db.begin_write()?;
+            let value = txn
+                .open_table::<K, V>(&table)?
+                .remove(&key)?
+                .map(|v| v.value())
+                .transpose()
+                .map_err(|e| {
+                    redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+                })?;
+            txn.commit()?;
+            Ok(value)
+        })
+        .await
+    }
+}
@@ -0,0 +1,74 @@
+use std::io::ErrorKind;
+use std::thread;
+use std::time::Duration;
+
+use crate::{Database, ReadTransaction, WriteTransaction};
+
+/// Runs transactions on a [`Database`], retrying them on transient I/O
+/// errors, see [`Database::with_retry`].
+///
+/// Only I/O errors of kind [`Interrupted`](ErrorKind::Interrupted),
+/// [`WouldBlock`](ErrorKind::WouldBlock) and
+/// [`TimedOut`](ErrorKind::TimedOut) are retried. Every other error,
+/// including corruption and values that fail to decode, is returned at once.
+pub struct Retrying<'db> {
+    db: &'db Database,
+    attempts: usize,
+    backoff: Duration,
+}
+
+impl<'db> Retrying<'db> {
+    pub(crate) fn new(db: &'db Database, attempts: usize, backoff: Duration) -> Self {
+        Self {
+            db,
+            attempts,
+            backoff,
+        }
+    }
+
+    /// Run `f` in a read transaction.
+    pub fn read<T, F>(&self, mut f: F) -> Result<T, redb::Error>
+    where
+        F: FnMut(&ReadTransaction) -> Result<T, redb::Error>,
+    {
+        self.retry(|| f(&self.db.begin_read()?))
+    }
+
+    /// Run `f` in a write transaction and commit it. A failed attempt is
+    /// aborted, and `f` runs again in a new transaction.
+    pub fn write<T, F>(&self, mut f: F) -> Result<T, redb::Error>
+    where
+        F: FnMut(&WriteTransaction) -> Result<T, redb::Error>,
+    {
+        self.retry(|| {
+            let txn = self.db.begin_write()?;
+            let res = f(&txn)?;
+            txn.commit()?;
+            Ok(res)
+        })
+    }
+
+    /// Make up to `attempts` attempts, waiting `backoff` after the first
+    /// failure and twice as long after each next one.
+    fn retry<T>(&self, mut f: impl FnMut() -> Result<T, redb::Error>) -> Result<T, redb::Error> {
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(redb::Error::Io(e)) if attempt < self.attempts && is_transient(&e) => {
+                    thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+fn is_transient(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+    )
+}
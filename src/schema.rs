@@ -0,0 +1,80 @@
+use crate::{fnv1a, TableError, WriteTransaction, FNV_OFFSET};
+
+/// The table of type tags, by table name, see [`type_fingerprint`].
+pub(crate) const TYPE_TAGS: &str = "redb_bincode.types";
+
+/// The table of generations, by table name: the number of changes made to a
+/// table through [`Table`](crate::Table), added to at each commit.
+pub(crate) const GENERATIONS: &str = "redb_bincode.generations";
+
+/// The table of the number of commits, in a single row, see
+/// [`ReadTransaction::snapshot_id`](crate::ReadTransaction::snapshot_id).
+pub(crate) const COMMITS: &str = "redb_bincode.commits";
+
+/// A hash of the names of the key and value types of a table, which
+/// [`WriteTransaction::create_table`] stores as its type tag.
+///
+/// Type names are not guaranteed to be stable across compiler releases, so a
+/// differing fingerprint is a hint to check the table, not proof of a
+/// mismatch.
+pub fn type_fingerprint<K, V>() -> u64 {
+    let hash = fnv1a(FNV_OFFSET, std::any::type_name::<K>().as_bytes());
+    fnv1a(fnv1a(hash, &[0]), std::any::type_name::<V>().as_bytes())
+}
+
+/// A table of a database, see [`Database::describe`].
+///
+/// [`Database::describe`]: crate::Database::describe
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableDescription {
+    pub name: String,
+    /// The number of entries.
+    pub len: u64,
+    /// The type tag stored when the table was created, if it was created by
+    /// [`WriteTransaction::create_table`].
+    pub type_fingerprint: Option<u64>,
+}
+
+/// A set of tables that should exist, see [`Database::ensure_schema`].
+/// Implement it with [`schema!`](crate::schema).
+///
+/// [`Database::ensure_schema`]: crate::Database::ensure_schema
+pub trait Schema {
+    /// The names of the tables.
+    const TABLES: &'static [&'static str];
+
+    /// Create the tables that don't exist yet.
+    fn create_tables(txn: &WriteTransaction) -> Result<(), TableError>;
+}
+
+/// Declares a [`Schema`] of tables with their key and value types.
+///
+/// ```ignore
+/// redb_bincode::schema! {
+///     pub struct AppSchema {
+///         users: String => User,
+///         events: (u64, u64) => Event,
+///     }
+/// }
+///
+/// db.ensure_schema::<AppSchema>()?;
+/// ```
+#[macro_export]
+macro_rules! schema {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident { $($table:ident : $k:ty => $v:ty),* $(,)? }) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        impl $crate::Schema for $name {
+            const TABLES: &'static [&'static str] = &[$(stringify!($table)),*];
+
+            fn create_tables(
+                txn: &$crate::WriteTransaction,
+            ) -> ::core::result::Result<(), $crate::TableError> {
+                $(txn.create_table::<$k, $v>(stringify!($table))?;)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    };
+}
@@ -0,0 +1,85 @@
+use std::borrow::Borrow;
+use std::sync::Arc;
+
+use redb::ReadableTable;
+
+use crate::{decode_key, tx, Lexicographical, ReadOnlyTable};
+
+/// A decoded entry of a snapshotted table.
+type Entry<K, V> = Result<(K, V), redb::Error>;
+
+/// A read transaction and a table opened in it.
+struct Snapshot<K, V> {
+    table: ReadOnlyTable<K, V, Lexicographical>,
+    // Kept so the snapshot owns the read transaction it was taken in.
+    _txn: tx::ReadTransaction,
+}
+
+/// A frozen view of a table, see [`Database::table_snapshot`].
+///
+/// Clones share the same read transaction, so they can be handed to worker
+/// threads that all see the table as it was when the snapshot was taken,
+/// while writes continue.
+///
+/// As long as any clone is alive, the pages of that version of the table
+/// can't be reused: a write-heavy database holding long-lived snapshots keeps
+/// growing by the pages that writes replace meanwhile.
+///
+/// [`Database::table_snapshot`]: crate::Database::table_snapshot
+pub struct TableSnapshot<K, V> {
+    inner: Arc<Snapshot<K, V>>,
+}
+
+impl<K, V> Clone for TableSnapshot<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, V> TableSnapshot<K, V>
+where
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+{
+    pub(crate) fn new(
+        txn: tx::ReadTransaction,
+        table: ReadOnlyTable<K, V, Lexicographical>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Snapshot { table, _txn: txn }),
+        }
+    }
+
+    /// Returns the snapshotted table.
+    pub fn as_table(&self) -> &ReadOnlyTable<K, V, Lexicographical> {
+        &self.inner.table
+    }
+
+    /// Get a value from the table by key.
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        self.inner
+            .table
+            .get(key)?
+            .map(|v| v.value())
+            .transpose()
+            .map_err(|e| redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+    }
+
+    /// Iterate over the entries of the table in sort order.
+    pub fn iter(&self) -> Result<impl Iterator<Item = Entry<K, V>> + '_, redb::Error> {
+        let table = &self.inner.table;
+        Ok(table.inner.iter()?.map(move |entry| {
+            let (key, value) = entry?;
+            Ok((
+                decode_key(key.value())?,
+                table.decode_value_limited(value.value())?,
+            ))
+        }))
+    }
+}
@@ -0,0 +1,33 @@
+use std::fmt;
+
+use crate::KEY_CONFIG;
+
+/// Panics unless each of `samples` encodes as a key to the bytes at the same
+/// position in `golden`, to catch changes of a key type's encoding in CI.
+///
+/// Encoded keys are stored, so a key type must keep encoding the same way
+/// for lookups and the sort order to keep working. For enums this is easy
+/// to break: bincode encodes the index of the variant in declaration order,
+/// so adding a variant anywhere but last, or reordering variants, changes
+/// the encoding of the variants after it, and keys of the new layout no
+/// longer find the stored ones. Record the output of
+/// `bincode::encode_to_vec(sample, KEY_CONFIG)` for a sample of every
+/// variant once, then check it with this function in a test.
+pub fn assert_stable_encoding<K>(samples: &[K], golden: &[&[u8]])
+where
+    K: bincode::Encode + fmt::Debug,
+{
+    assert_eq!(
+        samples.len(),
+        golden.len(),
+        "every sample needs a golden encoding"
+    );
+    for (sample, golden) in samples.iter().zip(golden) {
+        let encoded = bincode::encode_to_vec(sample, KEY_CONFIG).expect("encoding can't fail");
+        assert_eq!(
+            encoded.as_slice(),
+            *golden,
+            "the encoding of {sample:?} changed"
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Database;
+use crate::{Database, WriteTransaction};
 
 pub trait Readable<K, V>
 where
@@ -7,6 +7,16 @@ where
 {
     fn get<'a>(db: &Database, table: &'a str, key: &'a K) -> Result<Option<V>, redb::Error>;
 
+    /// Returns whether the table contains the key, without decoding its value.
+    fn exists(db: &Database, table: &str, key: &K) -> Result<bool, redb::Error>;
+
+    /// Get the values of several keys, in the order of `keys`, from a single
+    /// read transaction so that they are all read from the same snapshot.
+    fn get_batch(db: &Database, table: &str, keys: &[K]) -> Result<Vec<Option<V>>, redb::Error>
+    where
+        K: Sized,
+        V: Sized;
+
     fn get_many<'a>(
         db: &Database,
         table: &'a str,
@@ -17,6 +27,15 @@ where
         K: bincode::Decode,
         V: bincode::Decode;
 
+    /// Get the entries in a key range. The range is over encoded keys, which
+    /// sort like the keys themselves for unsigned integers, see
+    /// [`Encoding`](crate::Encoding).
+    fn get_range<R>(db: &Database, table: &str, range: R) -> Result<Vec<(K, V)>, redb::Error>
+    where
+        K: bincode::Decode,
+        V: bincode::Decode,
+        R: std::ops::RangeBounds<K>;
+
     fn get_many_where<'a, F>(
         db: &Database,
         table: &'a str,
@@ -49,6 +68,29 @@ impl<K: bincode::Encode + bincode::Decode, T: bincode::Encode + bincode::Decode>
         Ok(result)
     }
 
+    fn exists(db: &Database, table: &str, key: &K) -> Result<bool, redb::Error> {
+        let txn = db.begin_read()?;
+        let table = txn.open_table::<K, T>(table)?;
+        Ok(table.contains_key(key)?)
+    }
+
+    fn get_batch(db: &Database, table: &str, keys: &[K]) -> Result<Vec<Option<T>>, redb::Error> {
+        let txn = db.begin_read()?;
+        let table = txn.open_table::<K, T>(table)?;
+        let mut res = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value = table
+                .get(key)?
+                .map(|v| v.value())
+                .transpose()
+                .map_err(|e| {
+                    redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+                })?;
+            res.push(value);
+        }
+        Ok(res)
+    }
+
     fn get_many<'a>(
         db: &Database,
         table: &'a str,
@@ -64,6 +106,17 @@ impl<K: bincode::Encode + bincode::Decode, T: bincode::Encode + bincode::Decode>
         Ok(table.get_many(start, end)?)
     }
 
+    fn get_range<R>(db: &Database, table: &str, range: R) -> Result<Vec<(K, T)>, redb::Error>
+    where
+        K: bincode::Decode,
+        T: bincode::Decode,
+        R: std::ops::RangeBounds<K>,
+    {
+        let txn = db.begin_read()?;
+        let table = txn.open_table::<K, T>(table)?;
+        table.get_range::<K, R>(range)
+    }
+
     /// Get all entries that match the given predicate.
     /// Returns a vector of the entries, but does not remove them.
     /// To remove the entries, use `extract_many_where`.
@@ -100,6 +153,11 @@ where
     where
         F: Fn((K, V)) -> bool;
     //&'a V: bincode::Decode;
+
+    /// Insert `(table, key, value)` entries in a single write transaction.
+    /// Either all entries are committed or none are. For entries of
+    /// different types, use [`WriteBatch`].
+    fn insert_all(db: &Database, entries: &[(&str, &K, &V)]) -> Result<(), redb::Error>;
 }
 
 impl<
@@ -156,4 +214,49 @@ impl<
 
         Ok(res)
     }
+
+    fn insert_all(db: &Database, entries: &[(&str, &K, &T)]) -> Result<(), redb::Error> {
+        let txn = db.begin_write()?;
+        for (table, key, value) in entries {
+            let mut table = txn.open_table::<K, T>(table)?;
+            table.insert(*key, *value)?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+/// Inserts into several tables, possibly of different types, in one write
+/// transaction that commits them together.
+pub struct WriteBatch {
+    txn: WriteTransaction,
+}
+
+impl WriteBatch {
+    /// Start a batch in a new write transaction.
+    pub fn new(db: &Database) -> Result<Self, redb::Error> {
+        Ok(Self {
+            txn: db.begin_write()?,
+        })
+    }
+
+    /// Insert a key and value into a table of the batch.
+    pub fn insert<K, V>(
+        &mut self,
+        table: &str,
+        key: &K,
+        value: &V,
+    ) -> Result<&mut Self, redb::Error>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        self.txn.open_table::<K, V>(table)?.insert(key, value)?;
+        Ok(self)
+    }
+
+    /// Commit all inserts of the batch.
+    pub fn commit(self) -> Result<(), redb::Error> {
+        Ok(self.txn.commit()?)
+    }
 }
@@ -1,10 +1,28 @@
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
-use redb::{TableDefinition, TableError, TableHandle, UntypedTableHandle};
+use redb::{
+    SavepointError, StorageError, TableDefinition, TableError, TableHandle, UntypedTableHandle,
+};
 
-use super::{ReadOnlyTable, Table};
+use super::schema::{COMMITS, GENERATIONS, TYPE_TAGS};
+use super::watch::{ChangeKind, ChangeLog, Subscribers};
+#[cfg(feature = "zstd")]
+use super::CompressedTable;
+use super::{
+    type_fingerprint, BloomTable, Counters, Encoding, ExpiringTable, IndexedTable, Ordered,
+    OrderedLogTable, ReadOnlyTable, RingTable, Table, UntypedReadTable, VersionedTable,
+};
 use crate::sort;
 
+/// A read transaction.
+///
+/// A read transaction sees a snapshot of the database as of its start: writes
+/// committed afterwards are not visible, and uncommitted writes never are.
+/// Any number of read transactions can run alongside each other and alongside
+/// the write transaction, and beginning one doesn't wait for a commit in
+/// progress.
 pub struct ReadTransaction(redb::ReadTransaction);
 
 impl From<redb::ReadTransaction> for ReadTransaction {
@@ -17,6 +35,39 @@ impl ReadTransaction {
     pub fn as_raw(&self) -> &redb::ReadTransaction {
         &self.0
     }
+
+    /// The number of commits made through [`WriteTransaction::commit`] that
+    /// are visible to this transaction.
+    ///
+    /// Each commit counts itself in a metadata row, so the id is read from
+    /// this transaction's own snapshot: two transactions with the same id see
+    /// the same commits, and the count survives reopening the database.
+    /// Commits made through [`WriteTransaction::as_raw`] or
+    /// [`Database::as_raw`](crate::Database::as_raw) aren't counted.
+    pub fn snapshot_id(&self) -> Result<u64, redb::Error> {
+        let commits = match self.open_table::<(), u64>(COMMITS) {
+            Ok(commits) => commits,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let commits = commits
+            .get(&())?
+            .map(|commits| commits.value())
+            .transpose()
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+        Ok(commits.unwrap_or(0))
+    }
+    /// Open a table.
+    ///
+    /// Opening is a lookup of the table's root in redb's table of tables,
+    /// with no parsing involved, so opening the same table again is cheap and
+    /// there is no need to cache the handle.
+    ///
+    /// Read transactions never create tables: a missing table, such as a
+    /// misspelled name, fails with [`TableError::TableDoesNotExist`]. Only
+    /// [`WriteTransaction::open_table`] creates tables.
     pub fn open_table<K, V>(
         &self,
         name: &str,
@@ -24,14 +75,75 @@ impl ReadTransaction {
     where
         K: bincode::Encode + bincode::Decode,
         V: bincode::Encode + bincode::Decode,
+    {
+        self.open_table_with_encoding(name)
+    }
+
+    /// Open a table whose keys are encoded with fixed-width integers, see
+    /// [`Ordered`].
+    pub fn open_ordered_table<K, V>(
+        &self,
+        name: &str,
+    ) -> Result<ReadOnlyTable<K, V, sort::Lexicographical, Ordered>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        self.open_table_with_encoding(name)
+    }
+
+    /// Open a table encoded with `C` rather than the default [`Bincode`](crate::Bincode).
+    pub fn open_table_with_encoding<K, V, C>(
+        &self,
+        name: &str,
+    ) -> Result<ReadOnlyTable<K, V, sort::Lexicographical, C>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+        C: Encoding,
     {
         Ok(ReadOnlyTable {
             inner: self.0.open_table(redb::TableDefinition::new(name))?,
+            max_value_bytes: None,
             _k: PhantomData,
             _v: PhantomData,
+            _c: PhantomData,
+        })
+    }
+
+    /// Open a table whose values are not decoded if their encoded size is
+    /// over `max_bytes`, or if decoding them would allocate more than about
+    /// `max_bytes`, for tables holding untrusted data. Reading such a value
+    /// fails with [`DecodeError::LimitExceeded`] instead.
+    ///
+    /// The allocation limit is `max_bytes` rounded up to a power of two, and
+    /// at least 4 KiB.
+    ///
+    /// [`DecodeError::LimitExceeded`]: bincode::error::DecodeError::LimitExceeded
+    pub fn open_table_limited<K, V>(
+        &self,
+        name: &str,
+        max_bytes: usize,
+    ) -> Result<ReadOnlyTable<K, V, sort::Lexicographical>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        Ok(ReadOnlyTable {
+            max_value_bytes: Some(max_bytes),
+            ..self.open_table(name)?
         })
     }
 
+    /// Open a table without its key and value types, to inspect its encoded
+    /// bytes. Any table created by this crate can be opened, whatever its
+    /// types.
+    pub fn open_untyped_table(&self, name: &str) -> Result<UntypedReadTable, TableError> {
+        Ok(UntypedReadTable::from(
+            self.0.open_table(redb::TableDefinition::new(name))?,
+        ))
+    }
+
     pub fn list_tables(&self) -> Result<Vec<UntypedTableHandle>, redb::Error> {
         let res = self.0.list_tables()?.collect();
 
@@ -40,43 +152,503 @@ impl ReadTransaction {
     }
 }
 
-pub struct WriteTransaction(redb::WriteTransaction);
+pub struct WriteTransaction {
+    inner: redb::WriteTransaction,
+    changes: ChangeLog,
+}
 
 impl From<redb::WriteTransaction> for WriteTransaction {
     fn from(value: redb::WriteTransaction) -> Self {
-        Self(value)
+        Self::new(value, Subscribers::default())
     }
 }
 
 impl WriteTransaction {
+    pub(crate) fn new(inner: redb::WriteTransaction, subscribers: Subscribers) -> Self {
+        Self {
+            inner,
+            changes: ChangeLog::new(subscribers),
+        }
+    }
+
     pub fn as_raw(self) -> redb::WriteTransaction {
-        self.0
+        self.inner
     }
+    /// Open a table.
+    ///
+    /// As with [`ReadTransaction::open_table`], reopening a table is cheap.
+    /// A table can only be open once at a time in a write transaction, so
+    /// drop the previous handle before reopening it.
+    ///
+    /// redb has no read-only tables within a write transaction, but the
+    /// reading methods of [`Table`] take `&self`, and handles only borrow the
+    /// transaction, so any number of tables can be read from while another
+    /// is modified.
     pub fn open_table<K, V>(
         &self,
         name: &str,
-    ) -> Result<Table<K, V, sort::Lexicographical>, TableError>
+    ) -> Result<Table<'_, K, V, sort::Lexicographical>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        self.open_table_with_encoding(name)
+    }
+
+    /// Open a table whose keys are encoded with fixed-width integers, see
+    /// [`Ordered`].
+    pub fn open_ordered_table<K, V>(
+        &self,
+        name: &str,
+    ) -> Result<Table<'_, K, V, sort::Lexicographical, Ordered>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        self.open_table_with_encoding(name)
+    }
+
+    /// Open a table encoded with `C` rather than the default [`Bincode`](crate::Bincode).
+    pub fn open_table_with_encoding<K, V, C>(
+        &self,
+        name: &str,
+    ) -> Result<Table<'_, K, V, sort::Lexicographical, C>, TableError>
     where
         K: bincode::Encode + bincode::Decode,
         V: bincode::Encode + bincode::Decode,
+        C: Encoding,
     {
         Ok(Table {
-            inner: self.0.open_table(redb::TableDefinition::new(name))?,
+            inner: self.inner.open_table(redb::TableDefinition::new(name))?,
+            changes: self.changes.for_table(name),
+            max_value_bytes: None,
             _k: PhantomData,
             _v: PhantomData,
+            _c: PhantomData,
+        })
+    }
+
+    /// Open a table that refuses values whose encoded size is over
+    /// `max_bytes`: inserting one fails with [`StorageError::ValueTooLarge`],
+    /// and nothing is written.
+    pub fn open_table_max_value<K, V>(
+        &self,
+        name: &str,
+        max_bytes: usize,
+    ) -> Result<Table<'_, K, V, sort::Lexicographical>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        Ok(Table {
+            max_value_bytes: Some(max_bytes),
+            ..self.open_table(name)?
         })
     }
 
+    /// Create a table if it doesn't exist yet.
+    /// A new table is tagged with the [`type_fingerprint`] of `K` and `V`,
+    /// see [`Database::describe`](crate::Database::describe).
+    pub fn create_table<K, V>(&self, name: &str) -> Result<(), TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        self.open_table::<K, V>(name)?;
+
+        let mut tags = self.open_table::<String, u64>(TYPE_TAGS)?;
+        if tags.get(name)?.is_none() {
+            tags.insert(name, &type_fingerprint::<K, V>())?;
+        }
+        Ok(())
+    }
+
+    /// Open a table whose entries expire, see [`ExpiringTable`].
+    pub fn open_expiring_table<K, V>(
+        &self,
+        name: &str,
+    ) -> Result<ExpiringTable<'_, K, V, sort::Lexicographical>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        Ok(ExpiringTable::from(self.open_table::<K, (u64, V)>(name)?))
+    }
+
+    /// Open a table with an in-memory bloom filter of its keys, see
+    /// [`BloomTable`]. `expected_keys` sizes the filter when it is rebuilt.
+    pub fn open_bloom_table<K, V>(
+        &self,
+        name: &str,
+        expected_keys: usize,
+    ) -> Result<BloomTable<'_, K, V, sort::Lexicographical>, redb::Error>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        let generation = self
+            .open_table::<String, u64>(GENERATIONS)?
+            .get(name)?
+            .map(|generation| generation.value())
+            .transpose()
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+        BloomTable::new(
+            self.open_table(name)?,
+            self.open_table(&format!("{name}.bloom"))?,
+            generation.unwrap_or(0),
+            expected_keys,
+        )
+    }
+
+    /// Open a table with a secondary index, see [`IndexedTable`].
+    /// The index is stored in the `<name>.<index>` table, keyed by `extract`.
+    pub fn open_indexed_table<K, V, IK, F>(
+        &self,
+        name: &str,
+        index: &str,
+        extract: F,
+    ) -> Result<IndexedTable<'_, K, V, sort::Lexicographical, IK, F>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+        IK: bincode::Encode + bincode::Decode,
+        F: Fn(&V) -> IK,
+    {
+        Ok(IndexedTable::new(
+            self.open_table(name)?,
+            self.open_table(&format!("{name}.{index}"))?,
+            extract,
+        ))
+    }
+
+    /// Open a table whose values are tagged with a schema version, see
+    /// [`VersionedTable`]. Values are written with `version`, and rows of
+    /// other versions are decoded by `migrate`.
+    pub fn open_table_versioned<K, V, F>(
+        &self,
+        name: &str,
+        version: u16,
+        migrate: F,
+    ) -> Result<VersionedTable<'_, K, V, sort::Lexicographical, F>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+        F: Fn(u16, &[u8]) -> Result<V, redb::Error>,
+    {
+        Ok(VersionedTable::new(
+            self.open_table::<K, (u16, V)>(name)?,
+            version,
+            migrate,
+        ))
+    }
+
+    /// Open a table whose values are compressed with zstd at `level`, see
+    /// [`CompressedTable`].
+    #[cfg(feature = "zstd")]
+    pub fn open_compressed_table<K, V>(
+        &self,
+        name: &str,
+        level: i32,
+    ) -> Result<CompressedTable<'_, K, V, sort::Lexicographical>, TableError>
+    where
+        K: bincode::Encode + bincode::Decode,
+        V: bincode::Encode + bincode::Decode,
+    {
+        Ok(CompressedTable::new(self.open_table::<K, V>(name)?, level))
+    }
+
+    /// Open an append-only log, see [`OrderedLogTable`].
+    pub fn open_log_table<V>(&self, name: &str) -> Result<OrderedLogTable<'_, V>, TableError>
+    where
+        V: bincode::Encode + bincode::Decode,
+    {
+        Ok(OrderedLogTable::new(
+            self.open_ordered_table(name)?,
+            self.open_table(&format!("{name}.seq"))?,
+        ))
+    }
+
+    /// Open a log that keeps only its last `capacity` entries, see
+    /// [`RingTable`].
+    pub fn open_ring_table<V>(
+        &self,
+        name: &str,
+        capacity: u64,
+    ) -> Result<RingTable<'_, V>, TableError>
+    where
+        V: bincode::Encode + bincode::Decode,
+    {
+        Ok(RingTable::new(self.open_log_table(name)?, capacity))
+    }
+
+    /// Open a table of named counters, see [`Counters`].
+    pub fn open_counters(&self, name: &str) -> Result<Counters<'_>, TableError> {
+        Ok(Counters::from(self.open_table::<String, u64>(name)?))
+    }
+
+    /// Create a persistent savepoint of the database, which survives
+    /// restarts once this transaction commits. Create it before modifying
+    /// anything in this transaction.
+    /// Returns its id, for [`Database::restore_from_savepoint`].
+    ///
+    /// A savepoint keeps the pages it references from being reused, so the
+    /// file grows until it is removed with
+    /// [`WriteTransaction::delete_persistent_savepoint`].
+    ///
+    /// [`Database::restore_from_savepoint`]: crate::Database::restore_from_savepoint
+    pub fn persistent_savepoint(&self) -> Result<u64, SavepointError> {
+        self.inner.persistent_savepoint()
+    }
+
+    /// Roll the database back to a persistent savepoint.
+    /// The rollback takes effect when this transaction commits.
+    pub fn restore_persistent_savepoint(&mut self, id: u64) -> Result<(), SavepointError> {
+        let savepoint = self.inner.get_persistent_savepoint(id)?;
+        self.inner.restore_savepoint(&savepoint)
+    }
+
+    /// Delete a persistent savepoint, releasing the pages it references.
+    /// Returns whether it existed.
+    pub fn delete_persistent_savepoint(&self, id: u64) -> Result<bool, SavepointError> {
+        self.inner.delete_persistent_savepoint(id)
+    }
+
+    /// List the ids of all persistent savepoints.
+    pub fn list_persistent_savepoints(&self) -> Result<Vec<u64>, StorageError> {
+        Ok(self.inner.list_persistent_savepoints()?.collect())
+    }
+
+    /// Rename a table. Fails if `name` doesn't exist or `new_name` already
+    /// does.
+    ///
+    /// redb 2 can't rename tables, so the entries are moved into `new_name`
+    /// and `name` is deleted, which takes time proportional to the table.
+    /// Subscribers of `name` see each entry removed, and subscribers of
+    /// `new_name` see it inserted. The crate's metadata of the table moves
+    /// with it: its type tag and its `.bloom` and `.seq` sidecar tables.
+    pub fn rename_table(&self, name: &str, new_name: &str) -> Result<(), redb::Error> {
+        if self.table_exists(new_name)? {
+            return Err(redb::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("table {new_name} already exists"),
+            )));
+        }
+        if !self.table_exists(name)? {
+            return Err(TableError::TableDoesNotExist(name.to_owned()).into());
+        }
+        self.move_entries(name, new_name)?;
+
+        for sidecar in ["bloom", "seq"] {
+            let (from, to) = (format!("{name}.{sidecar}"), format!("{new_name}.{sidecar}"));
+            // A sidecar left behind by a deleted table doesn't describe the
+            // renamed one.
+            self.inner
+                .delete_table(
+                    TableDefinition::<sort::SortKey<sort::Lexicographical>, &[u8]>::new(&to),
+                )?;
+            if self.table_exists(&from)? {
+                self.move_entries(&from, &to)?;
+            }
+        }
+
+        let mut tags = self.open_table::<String, u64>(TYPE_TAGS)?;
+        let tag = tags
+            .remove(name)?
+            .map(|tag| tag.value())
+            .transpose()
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+        match tag {
+            Some(tag) => tags.insert(new_name, &tag)?,
+            None => tags.remove(new_name)?,
+        };
+        Ok(())
+    }
+
+    /// Moves the entries of `from` into `to` through [`Table`], so that the
+    /// changes reach subscribers, and deletes `from`.
+    fn move_entries(&self, from: &str, to: &str) -> Result<(), redb::Error> {
+        {
+            let mut src = self.open_table::<(), ()>(from)?;
+            let mut dst = self.open_table::<(), ()>(to)?;
+            for entry in src.inner.extract_from_if::<&[u8], _>(.., |_, _| true)? {
+                let (key, value) = entry?;
+                src.changes.record(key.value(), ChangeKind::Remove);
+                dst.insert_bytes(key.value(), value.value())?;
+            }
+        }
+        self.inner.delete_table(
+            TableDefinition::<sort::SortKey<sort::Lexicographical>, &[u8]>::new(from),
+        )?;
+        Ok(())
+    }
+
+    /// Returns whether a table named `name` exists.
+    pub(crate) fn table_exists(&self, name: &str) -> Result<bool, StorageError> {
+        Ok(self.inner.list_tables()?.any(|table| table.name() == name))
+    }
+
+    /// Opens a table as stored, without a key or value type.
+    pub(crate) fn open_raw_table(
+        &self,
+        name: &str,
+    ) -> Result<redb::Table<'_, sort::SortKey<sort::Lexicographical>, &'static [u8]>, TableError>
+    {
+        self.inner.open_table(TableDefinition::new(name))
+    }
 
     pub fn delete_table<K, V>(&self, def: TableDefinition<K, V>) -> Result<bool, TableError> 
     where 
         K: redb::Key + 'static,
         V: redb::Value + 'static,
     {
-        self.0.delete_table(def)
+        self.inner.delete_table(def)
     }
 
+    /// Set how durable the commit of this transaction is, see [`Durability`].
+    ///
+    /// [`Durability`]: crate::Durability
+    pub fn set_durability(&mut self, durability: crate::Durability) {
+        self.inner.set_durability(durability);
+    }
+
+    /// Commit the transaction.
+    /// Subscribers of the touched tables are notified once the commit succeeds.
+    ///
+    /// Since write transactions are serialized, a [`CommitError`] is never a
+    /// conflict with another writer: it reports a storage failure, and
+    /// retrying the same transaction is not expected to help.
+    ///
+    /// [`CommitError`]: redb::CommitError
     pub fn commit(self) -> Result<(), redb::CommitError> {
-        self.0.commit()
+        self.update_metadata().map_err(|e| match e {
+            redb::Error::Io(e) => StorageError::Io(e),
+            e => StorageError::Corrupted(format!("updating metadata: {e}")),
+        })?;
+        self.inner.commit()?;
+        self.changes.publish();
+        Ok(())
+    }
+
+    /// Counts the commit, see [`ReadTransaction::snapshot_id`], and adds the
+    /// changes made in this transaction to the tables' generations.
+    fn update_metadata(&self) -> Result<(), redb::Error> {
+        let mut commits = self.open_table::<(), u64>(COMMITS)?;
+        let count = commits
+            .get(&())?
+            .map(|count| count.value())
+            .transpose()
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+        commits.insert(&(), &(count.unwrap_or(0) + 1))?;
+        drop(commits);
+
+        let writes = self.changes.take_writes();
+        if writes.is_empty() {
+            return Ok(());
+        }
+        let mut generations = self.open_table::<String, u64>(GENERATIONS)?;
+        for (table, count) in writes {
+            let generation = generations
+                .get(&table)?
+                .map(|generation| generation.value())
+                .transpose()
+                .map_err(|e| {
+                    redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+                })?;
+            generations.insert(&table, &generation.unwrap_or(0).wrapping_add(count))?;
+        }
+        Ok(())
+    }
+
+    /// Abort the transaction, discarding its changes.
+    /// Dropping a transaction without committing it aborts it too.
+    pub fn abort(self) -> Result<(), StorageError> {
+        self.inner.abort()
+    }
+
+    /// Commit the transaction if `check` accepts its pending state, and
+    /// abort it otherwise, such as when a post-condition fails.
+    /// Returns whether it committed.
+    pub fn commit_if<F>(self, check: F) -> Result<bool, redb::Error>
+    where
+        F: FnOnce(&WriteTransaction) -> bool,
+    {
+        if check(&self) {
+            self.commit()?;
+            Ok(true)
+        } else {
+            self.abort()?;
+            Ok(false)
+        }
+    }
+}
+
+/// A write transaction that must be ended explicitly, see
+/// [`Database::write_guard`](crate::Database::write_guard).
+///
+/// Dereferences to the [`WriteTransaction`]. A guard dropped without calling
+/// [`WriteGuard::commit`] or [`WriteGuard::abort`], such as by an early return
+/// or a panic, aborts the transaction: a commit from `drop` couldn't report
+/// its errors, so nothing is ever committed implicitly. Guards dropped other
+/// than by a panic are counted by
+/// [`Database::abandoned_write_guards`](crate::Database::abandoned_write_guards).
+pub struct WriteGuard {
+    txn: Option<WriteTransaction>,
+    abandoned: Arc<AtomicU64>,
+}
+
+impl WriteGuard {
+    pub(crate) fn new(txn: WriteTransaction, abandoned: Arc<AtomicU64>) -> Self {
+        Self {
+            txn: Some(txn),
+            abandoned,
+        }
+    }
+
+    /// Commit the transaction.
+    pub fn commit(mut self) -> Result<(), redb::CommitError> {
+        self.take().commit()
+    }
+
+    /// Abort the transaction, discarding its changes.
+    pub fn abort(mut self) -> Result<(), StorageError> {
+        self.take().abort()
+    }
+
+    fn take(&mut self) -> WriteTransaction {
+        self.txn.take().expect("guard already ended")
+    }
+}
+
+impl std::ops::Deref for WriteGuard {
+    type Target = WriteTransaction;
+
+    fn deref(&self) -> &WriteTransaction {
+        self.txn.as_ref().expect("guard already ended")
+    }
+}
+
+impl std::ops::DerefMut for WriteGuard {
+    fn deref_mut(&mut self) -> &mut WriteTransaction {
+        self.txn.as_mut().expect("guard already ended")
+    }
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        let Some(txn) = self.txn.take() else {
+            return;
+        };
+        if !std::thread::panicking() {
+            self.abandoned.fetch_add(1, Ordering::Relaxed);
+        }
+        // Dropping the transaction aborts it too, so a failure here leaves
+        // nothing to clean up.
+        let _ = txn.abort();
     }
 }
@@ -0,0 +1,50 @@
+use redb::{ReadableTable, ReadableTableMetadata, StorageError, TableStats};
+
+use crate::{sort, Lexicographical, RawEntry};
+
+/// A table of this crate opened without its key and value types, to inspect
+/// its encoded bytes, see [`ReadTransaction::open_untyped_table`].
+///
+/// [`ReadTransaction::open_untyped_table`]: crate::ReadTransaction::open_untyped_table
+pub struct UntypedReadTable {
+    inner: redb::ReadOnlyTable<sort::SortKey<Lexicographical>, &'static [u8]>,
+}
+
+impl From<redb::ReadOnlyTable<sort::SortKey<Lexicographical>, &'static [u8]>> for UntypedReadTable {
+    fn from(inner: redb::ReadOnlyTable<sort::SortKey<Lexicographical>, &'static [u8]>) -> Self {
+        Self { inner }
+    }
+}
+
+impl UntypedReadTable {
+    /// Returns the number of entries in the table.
+    pub fn len(&self) -> Result<u64, StorageError> {
+        self.inner.len()
+    }
+
+    /// Returns whether the table has no entries.
+    pub fn is_empty(&self) -> Result<bool, StorageError> {
+        self.inner.is_empty()
+    }
+
+    /// Returns the storage statistics of the table.
+    pub fn stats(&self) -> Result<TableStats, StorageError> {
+        self.inner.stats()
+    }
+
+    /// Get the encoded value of an encoded key.
+    pub fn get_raw(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.inner.get(key)?.map(|v| v.value().to_vec()))
+    }
+
+    /// Iterate over the encoded key and value bytes of the table, in sort
+    /// order.
+    pub fn iter_raw(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<RawEntry, StorageError>> + '_, StorageError> {
+        Ok(self.inner.iter()?.map(|entry| {
+            let (key, value) = entry?;
+            Ok((key.value().to_vec(), value.value().to_vec()))
+        }))
+    }
+}
@@ -0,0 +1,123 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use redb::StorageError;
+
+use super::{
+    decode_value, with_encode_key_buf, with_encode_value_buf, SortOrder, Table, KEY_CONFIG,
+    VALUE_CONFIG,
+};
+
+/// A mutable table whose values are tagged with a schema version.
+///
+/// Values are stored as `(u16 version, V)`. Inserts write the current
+/// version; reads of a row with another version pass its version and the
+/// encoded bytes that follow it to `migrate`, which decodes them into the
+/// current `V`. Old rows are migrated on read only, and stay as they are
+/// until they are written again.
+///
+/// Only tables of the default [`Bincode`](crate::Bincode) encoding are
+/// supported.
+pub struct VersionedTable<'txn, K, V, S, F>
+where
+    S: SortOrder + fmt::Debug + 'static,
+{
+    inner: Table<'txn, K, (u16, V), S>,
+    version: u16,
+    migrate: F,
+}
+
+impl<'txn, K, V, S, F> VersionedTable<'txn, K, V, S, F>
+where
+    S: SortOrder + fmt::Debug + 'static,
+    K: bincode::Encode + bincode::Decode,
+    V: bincode::Encode + bincode::Decode,
+    F: Fn(u16, &[u8]) -> Result<V, redb::Error>,
+{
+    pub(crate) fn new(inner: Table<'txn, K, (u16, V), S>, version: u16, migrate: F) -> Self {
+        Self {
+            inner,
+            version,
+            migrate,
+        }
+    }
+
+    /// Returns the underlying table of `(version, value)` pairs. Decoding
+    /// its values fails for rows of other versions.
+    pub fn as_table(&self) -> &Table<'txn, K, (u16, V), S> {
+        &self.inner
+    }
+
+    /// The version that inserts are written with.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Get a value from the table by key, migrating it if it was written
+    /// with another version.
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>, redb::Error>
+    where
+        K: Borrow<Q>,
+        Q: bincode::Encode + ?Sized,
+    {
+        let Some(guard) = self.inner.get(key)? else {
+            return Ok(None);
+        };
+        self.decode(guard.inner.value()).map(Some)
+    }
+
+    /// Inserts a key and value into the table, tagged with the current
+    /// version.
+    pub fn insert<KQ, VQ>(&mut self, key: &KQ, value: &VQ) -> Result<(), StorageError>
+    where
+        K: Borrow<KQ>,
+        V: Borrow<VQ>,
+        KQ: bincode::Encode + ?Sized,
+        VQ: bincode::Encode + ?Sized,
+    {
+        unsafe {
+            with_encode_key_buf(|key_buf| {
+                let key_size = bincode::encode_into_std_write(key, key_buf, KEY_CONFIG)
+                    .expect("encoding can't fail");
+
+                with_encode_value_buf(|value_buf| {
+                    let value_size = bincode::encode_into_std_write(
+                        (self.version, value),
+                        value_buf,
+                        VALUE_CONFIG,
+                    )
+                    .expect("encoding can't fail");
+
+                    self.inner
+                        .insert_bytes(&key_buf[..key_size], &value_buf[..value_size])?;
+                    Ok(())
+                })
+            })
+        }
+    }
+
+    /// Remove a value from the table by key.
+    /// Returns the value that was removed, migrated to the current version.
+    pub fn remove<KQ>(&mut self, key: &KQ) -> Result<Option<V>, redb::Error>
+    where
+        K: Borrow<KQ>,
+        KQ: bincode::Encode + ?Sized,
+    {
+        let Some(bytes) = self.inner.remove(key)?.map(|v| v.inner.value().to_vec()) else {
+            return Ok(None);
+        };
+        self.decode(&bytes).map(Some)
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<V, redb::Error> {
+        let (version, size): (u16, usize) = bincode::decode_from_slice(data, VALUE_CONFIG)
+            .map_err(|e| {
+                redb::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })?;
+        if version == self.version {
+            decode_value(&data[size..])
+        } else {
+            (self.migrate)(version, &data[size..])
+        }
+    }
+}
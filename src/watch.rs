@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// What happened to a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Insert,
+    Remove,
+}
+
+/// A change to a table, delivered to subscribers once the write transaction
+/// that made it has committed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    /// The encoded key, decode it with [`KEY_CONFIG`](crate::KEY_CONFIG).
+    pub key: Vec<u8>,
+    pub kind: ChangeKind,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Subscribers of a database, by table name.
+#[derive(Default, Clone)]
+pub(crate) struct Subscribers(Arc<Mutex<HashMap<String, Vec<Sender<ChangeEvent>>>>>);
+
+impl Subscribers {
+    pub(crate) fn subscribe(&self, table: &str) -> Receiver<ChangeEvent> {
+        let (tx, rx) = mpsc::channel();
+        lock(&self.0).entry(table.to_owned()).or_default().push(tx);
+        rx
+    }
+
+    fn is_watched(&self, table: &str) -> bool {
+        lock(&self.0).get(table).is_some_and(|s| !s.is_empty())
+    }
+
+    /// Send committed changes, dropping subscribers whose receiver is gone.
+    fn publish(&self, changes: HashMap<String, Vec<ChangeEvent>>) {
+        let mut subscribers = lock(&self.0);
+        for (table, events) in changes {
+            let Some(senders) = subscribers.get_mut(&table) else {
+                continue;
+            };
+            senders.retain(|sender| events.iter().all(|e| sender.send(e.clone()).is_ok()));
+        }
+    }
+}
+
+/// Changes made in a write transaction, not yet committed.
+#[derive(Default)]
+pub(crate) struct ChangeLog {
+    subscribers: Subscribers,
+    events: Mutex<HashMap<String, Vec<ChangeEvent>>>,
+    /// The number of changes to each table, watched or not.
+    writes: Mutex<HashMap<String, u64>>,
+}
+
+impl ChangeLog {
+    pub(crate) fn new(subscribers: Subscribers) -> Self {
+        Self {
+            subscribers,
+            events: Mutex::default(),
+            writes: Mutex::default(),
+        }
+    }
+
+    /// Returns the recorder for a table. Only the changes of watched tables
+    /// are kept, the others are just counted.
+    pub(crate) fn for_table(&self, table: &str) -> TableChanges<'_> {
+        TableChanges {
+            log: self,
+            table: table.to_owned(),
+            watched: self.subscribers.is_watched(table),
+        }
+    }
+
+    /// Returns the number of changes to each table, resetting them.
+    pub(crate) fn take_writes(&self) -> HashMap<String, u64> {
+        std::mem::take(&mut lock(&self.writes))
+    }
+
+    /// Deliver the recorded changes. Must only be called after commit.
+    pub(crate) fn publish(self) {
+        let events = self.events.into_inner().unwrap_or_else(|e| e.into_inner());
+        self.subscribers.publish(events);
+    }
+}
+
+/// Records the changes made through a single table.
+pub(crate) struct TableChanges<'txn> {
+    log: &'txn ChangeLog,
+    table: String,
+    watched: bool,
+}
+
+impl TableChanges<'_> {
+    pub(crate) fn record(&self, key: &[u8], kind: ChangeKind) {
+        *lock(&self.log.writes)
+            .entry(self.table.clone())
+            .or_default() += 1;
+        if self.watched {
+            lock(&self.log.events)
+                .entry(self.table.clone())
+                .or_default()
+                .push(ChangeEvent {
+                    key: key.to_vec(),
+                    kind,
+                });
+        }
+    }
+
+    /// Returns the number of changes to the table so far in the transaction.
+    pub(crate) fn writes(&self) -> u64 {
+        lock(&self.log.writes)
+            .get(&self.table)
+            .copied()
+            .unwrap_or(0)
+    }
+}